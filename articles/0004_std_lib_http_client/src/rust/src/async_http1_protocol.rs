@@ -0,0 +1,378 @@
+use crate::async_transport::AsyncTransport;
+use crate::error::{Error, HttpClientError, Result, TransportError};
+use crate::http1_protocol::{
+    chunked_response_view, content_length_of, DEFAULT_MAX_HEADER_COUNT, find_header_end, into_safe_response, is_chunked,
+    parse_response_view, scan_chunked, skip_leading_empty_lines, status_forbids_body, write_request_head,
+};
+use crate::http_protocol::{DEFAULT_USER_AGENT, HttpMethod, HttpRequest, SafeHttpResponse, UnsafeHttpResponse};
+
+// Mirrors `Http1Protocol`'s request/response cycle over an `AsyncTransport`. Framing and
+// parsing are the same functions the sync path uses; only the I/O is awaited.
+pub struct AsyncHttp1Protocol<T: AsyncTransport> {
+    transport: T,
+    buffer: Vec<u8>,
+    header_size: usize,
+    content_length: Option<usize>,
+    chunked: bool,
+    decoded_body: Vec<u8>,
+    trailer_block: Option<std::ops::Range<usize>>,
+    expect_body: bool,
+    user_agent: String,
+    max_header_count: usize,
+}
+
+impl<T: AsyncTransport> AsyncHttp1Protocol<T> {
+
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            buffer: Vec::with_capacity(1024),
+            header_size: 0,
+            content_length: None,
+            chunked: false,
+            decoded_body: Vec::new(),
+            trailer_block: None,
+            expect_body: true,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_header_count: DEFAULT_MAX_HEADER_COUNT,
+        }
+    }
+
+    pub fn set_user_agent(&mut self, user_agent: &str) {
+        self.user_agent = user_agent.to_string();
+    }
+
+    pub fn set_max_header_count(&mut self, max_header_count: usize) {
+        self.max_header_count = max_header_count;
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    pub async fn connect(&mut self, host: &str, port: u16) -> Result<()> {
+        self.transport.connect(host, port).await
+    }
+
+    pub async fn disconnect(&mut self) -> Result<()> {
+        self.transport.close().await
+    }
+
+    pub async fn perform_request_unsafe<'a>(&'a mut self, request: &HttpRequest<'_>) -> Result<UnsafeHttpResponse<'a>> {
+        self.ensure_connected()?;
+        self.buffer.clear();
+        write_request_head(&mut self.buffer, request, &self.user_agent)?;
+        if !request.body.is_empty() && request.method.allows_body() {
+            self.buffer.extend_from_slice(request.body);
+        }
+
+        let mut written = 0;
+        while written < self.buffer.len() {
+            match self.transport.write(&self.buffer[written..]).await? {
+                0 => return Err(Error::Transport(TransportError::ConnectionClosed)),
+                n => written += n,
+            }
+        }
+
+        self.expect_body = !matches!(request.method, HttpMethod::Head | HttpMethod::Connect);
+        self.read_response().await?;
+        if let Some(trailers) = &self.trailer_block {
+            return chunked_response_view(
+                &self.buffer,
+                self.header_size,
+                &self.decoded_body,
+                trailers.clone(),
+                self.max_header_count,
+            );
+        }
+        parse_response_view(&self.buffer, self.header_size, self.content_length, self.expect_body, self.max_header_count)
+    }
+
+    pub async fn perform_request_safe(&mut self, request: &HttpRequest<'_>) -> Result<SafeHttpResponse> {
+        let unsafe_res = self.perform_request_unsafe(request).await?;
+        Ok(into_safe_response(unsafe_res))
+    }
+
+    fn ensure_connected(&self) -> Result<()> {
+        if self.transport.is_connected() {
+            Ok(())
+        } else {
+            Err(Error::Transport(TransportError::NotConnected))
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<()> {
+        self.buffer.clear();
+        self.header_size = 0;
+        self.content_length = None;
+        self.chunked = false;
+        self.decoded_body.clear();
+        self.trailer_block = None;
+        let mut leading_lines_skipped = 0;
+
+        loop {
+            let old_len = self.buffer.len();
+            let read_amount = (self.buffer.capacity() - old_len).max(1024);
+            self.buffer.resize(old_len + read_amount, 0);
+
+            let bytes_read = match self.transport.read(&mut self.buffer[old_len..]).await {
+                Ok(n) => n,
+                Err(Error::Transport(TransportError::ConnectionClosed)) => {
+                    self.buffer.truncate(old_len);
+                    if self.buffer.is_empty() {
+                        return Err(Error::Transport(TransportError::ConnectionClosed));
+                    }
+                    if let Some(content_len) = self.content_length
+                        && self.buffer.len() < self.header_size + content_len
+                    {
+                        return Err(Error::Http(HttpClientError::HttpParseFailure));
+                    }
+                    // A complete chunked body ends the loop before any further read.
+                    if self.chunked && self.expect_body {
+                        return Err(Error::Http(HttpClientError::HttpParseFailure));
+                    }
+                    break;
+                }
+                Err(e) => {
+                    self.buffer.truncate(old_len);
+                    return Err(e);
+                }
+            };
+            self.buffer.truncate(old_len + bytes_read);
+
+            // There is no lenient mode here, so one stray blank line is all that is accepted.
+            if self.header_size == 0 {
+                skip_leading_empty_lines(&mut self.buffer, &mut leading_lines_skipped, 1)?;
+            }
+
+            if self.header_size == 0
+                && let Some(header_end) = find_header_end(&self.buffer)
+            {
+                self.header_size = header_end;
+                let headers_view = &self.buffer[..header_end];
+                if status_forbids_body(headers_view) {
+                    self.expect_body = false;
+                }
+                // Transfer-Encoding wins over any Content-Length sent alongside it.
+                self.chunked = is_chunked(headers_view);
+                self.content_length = if self.chunked { None } else { content_length_of(headers_view)? };
+            }
+
+            if self.header_size != 0 && !self.expect_body {
+                break;
+            }
+
+            if self.chunked
+                && let Some(layout) = scan_chunked(&self.buffer[self.header_size..])?
+            {
+                let body = &self.buffer[self.header_size..];
+                for chunk in &layout.chunks {
+                    self.decoded_body.extend_from_slice(&body[chunk.clone()]);
+                }
+                self.trailer_block = Some(self.header_size + layout.trailers.start..self.header_size + layout.trailers.end);
+                break;
+            }
+
+            if let Some(content_len) = self.content_length
+                && self.buffer.len() >= self.header_size + content_len
+            {
+                break;
+            }
+        }
+
+        if self.header_size == 0 && !self.buffer.is_empty() {
+            return Err(Error::Http(HttpClientError::HttpParseFailure));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_transport::{AsyncTcpTransport, AsyncUnixTransport};
+    use crate::http_protocol::HttpHeaderView;
+    use std::future::Future;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, UnixListener};
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_io()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    async fn respond_once<S: AsyncReadExt + AsyncWriteExt + Unpin>(mut stream: S, response: &[u8]) -> Vec<u8> {
+        let mut buffer = vec![0u8; 1024];
+        let n = stream.read(&mut buffer).await.unwrap();
+        stream.write_all(response).await.unwrap();
+        buffer.truncate(n);
+        buffer
+    }
+
+    #[test]
+    fn performs_async_get_over_tcp() {
+        block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond_once(stream, b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nasync").await
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            protocol.connect("127.0.0.1", port).await.unwrap();
+
+            let request = HttpRequest {
+                method: HttpMethod::Get,
+                path: "/async",
+                body: &[],
+                headers: vec![HttpHeaderView { key: "Host", value: "localhost" }],
+            };
+            let res = protocol.perform_request_safe(&request).await.unwrap();
+
+            assert_eq!(res.status_code, 200);
+            assert_eq!(res.body, b"async");
+            assert!(server.await.unwrap().starts_with(b"GET /async HTTP/1.1\r\nHost: localhost\r\n"));
+            protocol.disconnect().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn performs_async_head_over_unix_socket() {
+        block_on(async {
+            let path = format!("/tmp/httpc_rust_async_test_{}", std::process::id());
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond_once(stream, b"HTTP/1.1 200 OK\r\nContent-Length: 42\r\n\r\n").await
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncUnixTransport::new());
+            protocol.connect(&path, 0).await.unwrap();
+
+            let request = HttpRequest { method: HttpMethod::Head, path: "/", body: &[], headers: vec![] };
+            let res = protocol.perform_request_unsafe(&request).await.unwrap();
+
+            assert_eq!(res.status_code, 200);
+            assert!(res.body.is_empty());
+            assert_eq!(res.content_length, Some(42));
+            server.await.unwrap();
+            let _ = std::fs::remove_file(&path);
+        });
+    }
+
+    #[test]
+    fn async_response_decodes_chunked_body_and_trailers() {
+        block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            // The server holds the connection open until the client leaves, so only the final
+            // chunk can end the body.
+            let server = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                respond_once(
+                    &mut stream,
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6\r\n async\r\n0\r\nX-Sum: 7\r\n\r\n",
+                )
+                .await;
+                let _ = stream.read(&mut [0u8; 16]).await;
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            protocol.connect("127.0.0.1", port).await.unwrap();
+
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            let res = protocol.perform_request_safe(&request).await.unwrap();
+
+            assert_eq!(res.body, b"hello async");
+            assert_eq!(res.content_length, Some(11));
+            assert_eq!(res.header("X-Sum"), Some("7"));
+            assert_eq!(res.trailers().len(), 1);
+            protocol.disconnect().await.unwrap();
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn async_request_without_connection_is_not_connected() {
+        block_on(async {
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+            let err = protocol.perform_request_safe(&request).await.unwrap_err();
+            assert_eq!(err, Error::Transport(TransportError::NotConnected));
+
+            let err = protocol.perform_request_unsafe(&request).await.unwrap_err();
+            assert_eq!(err, Error::Transport(TransportError::NotConnected));
+        });
+    }
+
+    #[test]
+    fn async_response_skips_one_leading_empty_line() {
+        block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond_once(stream, b"\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            protocol.connect("127.0.0.1", port).await.unwrap();
+
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            let res = protocol.perform_request_safe(&request).await.unwrap();
+
+            assert_eq!(res.status_code, 200);
+            assert_eq!(res.body, b"ok");
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn async_response_rejects_repeated_leading_empty_lines() {
+        block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond_once(stream, b"\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            protocol.connect("127.0.0.1", port).await.unwrap();
+
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            let err = protocol.perform_request_safe(&request).await.unwrap_err();
+
+            assert_eq!(err, Error::Http(HttpClientError::HttpParseFailure));
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn async_read_fails_on_truncated_body() {
+        block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                respond_once(stream, b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort").await
+            });
+
+            let mut protocol = AsyncHttp1Protocol::new(AsyncTcpTransport::new());
+            protocol.connect("127.0.0.1", port).await.unwrap();
+
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            assert_eq!(
+                protocol.perform_request_safe(&request).await.unwrap_err(),
+                Error::Http(HttpClientError::HttpParseFailure)
+            );
+            server.await.unwrap();
+        });
+    }
+}
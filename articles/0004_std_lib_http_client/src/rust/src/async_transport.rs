@@ -0,0 +1,126 @@
+use crate::error::{Error, IoPhase, Result, TransportError};
+use std::future::Future;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UnixStream};
+
+// The async counterpart of `Transport`; same contract, with each call awaited.
+pub trait AsyncTransport {
+    fn connect(&mut self, host: &str, port: u16) -> impl Future<Output = Result<()>> + Send;
+
+    fn write(&mut self, buf: &[u8]) -> impl Future<Output = Result<usize>> + Send;
+
+    fn read(&mut self, buf: &mut [u8]) -> impl Future<Output = Result<usize>> + Send;
+
+    fn close(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    // Whether a connection is currently open. Transports that cannot tell report true
+    // and leave it to the first read or write.
+    fn is_connected(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct AsyncTcpTransport {
+    stream: Option<TcpStream>,
+}
+
+impl AsyncTcpTransport {
+    pub fn new() -> Self {
+        Self { stream: None }
+    }
+}
+
+impl AsyncTransport for AsyncTcpTransport {
+    async fn connect(&mut self, host: &str, port: u16) -> Result<()> {
+        let addrs: Vec<_> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| Error::from_io(e, IoPhase::Resolve))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(Error::Transport(TransportError::DnsFailure));
+        }
+
+        let stream = TcpStream::connect(&addrs[..]).await.map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        stream.set_nodelay(true).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match &mut self.stream {
+            Some(stream) => stream.write(buf).await.map_err(|e| Error::from_io(e, IoPhase::Write)),
+            None => Err(Error::Transport(TransportError::SocketWriteFailure)),
+        }
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let Some(stream) = &mut self.stream else {
+            return Err(Error::Transport(TransportError::SocketReadFailure));
+        };
+        let bytes_read = stream.read(buf).await.map_err(|e| Error::from_io(e, IoPhase::Read))?;
+        if bytes_read == 0 && !buf.is_empty() {
+            return Err(Error::Transport(TransportError::ConnectionClosed));
+        }
+        Ok(bytes_read)
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.shutdown().await.map_err(|e| Error::from_io(e, IoPhase::Close))?;
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+}
+
+#[derive(Default)]
+pub struct AsyncUnixTransport {
+    stream: Option<UnixStream>,
+}
+
+impl AsyncUnixTransport {
+    pub fn new() -> Self {
+        Self { stream: None }
+    }
+}
+
+impl AsyncTransport for AsyncUnixTransport {
+    async fn connect(&mut self, path: &str, _port: u16) -> Result<()> {
+        let stream = UnixStream::connect(path).await.map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    async fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match &mut self.stream {
+            Some(stream) => stream.write(buf).await.map_err(|e| Error::from_io(e, IoPhase::Write)),
+            None => Err(Error::Transport(TransportError::SocketWriteFailure)),
+        }
+    }
+
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let Some(stream) = &mut self.stream else {
+            return Err(Error::Transport(TransportError::SocketReadFailure));
+        };
+        let bytes_read = stream.read(buf).await.map_err(|e| Error::from_io(e, IoPhase::Read))?;
+        if bytes_read == 0 && !buf.is_empty() {
+            return Err(Error::Transport(TransportError::ConnectionClosed));
+        }
+        Ok(bytes_read)
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.shutdown().await.map_err(|e| Error::from_io(e, IoPhase::Close))?;
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+}
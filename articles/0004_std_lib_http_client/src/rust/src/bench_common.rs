@@ -0,0 +1,113 @@
+// Benchmark plumbing shared by httprust_client and reqwest_client, so both clients send
+// identical payloads and check responses the same way. Included with #[path] from each
+// binary rather than exported by the library.
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The server echoes each request as [payload][16 hex digit checksum][19 digit timestamp].
+pub const CHECKSUM_LEN: usize = 16;
+pub const TIMESTAMP_LEN: usize = 19;
+
+#[derive(Debug)]
+pub struct BenchmarkData {
+    pub sizes: Vec<u64>,
+    pub data_block: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct VerifiedResponse {
+    pub server_timestamp: u64,
+    pub checksum_ok: bool,
+}
+
+pub fn read_benchmark_data(filename: &str) -> Result<BenchmarkData, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+
+    let mut num_requests_buf = [0u8; 8];
+    file.read_exact(&mut num_requests_buf)?;
+    let num_requests = u64::from_le_bytes(num_requests_buf);
+
+    let mut sizes_bytes = vec![0u8; (num_requests as usize) * 8];
+    file.read_exact(&mut sizes_bytes)?;
+    let sizes = sizes_bytes.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect();
+
+    let mut data_block = Vec::new();
+    file.read_to_end(&mut data_block)?;
+
+    Ok(BenchmarkData { sizes, data_block })
+}
+
+pub fn xor_checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0, |acc, &byte| acc ^ u64::from(byte))
+}
+
+pub fn get_nanoseconds() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+// The body of request `i`: its slice of the data block, followed by the checksum the
+// server echoes back when verification is on.
+pub fn build_payload(data: &BenchmarkData, i: u64, verify: bool) -> Vec<u8> {
+    let req_size = data.sizes[i as usize % data.sizes.len()] as usize;
+    let body_slice = &data.data_block[..req_size];
+
+    let mut payload = body_slice.to_vec();
+    if verify {
+        payload.extend_from_slice(format!("{:016x}", xor_checksum(body_slice)).as_bytes());
+    }
+    payload
+}
+
+// Extracts the server timestamp and, when `verify` is set, checks the echoed checksum.
+// A body too short to hold the checksum fails verification rather than erroring.
+pub fn verify_response(body: &[u8], verify: bool) -> Result<VerifiedResponse, Box<dyn Error>> {
+    let timestamp_start = body.len().checked_sub(TIMESTAMP_LEN).ok_or("Response body too short")?;
+    let server_timestamp = std::str::from_utf8(&body[timestamp_start..])?.parse::<u64>()?;
+
+    let checksum_ok = !verify || match timestamp_start.checked_sub(CHECKSUM_LEN) {
+        Some(checksum_start) => {
+            let checksum_hex = std::str::from_utf8(&body[checksum_start..timestamp_start])?;
+            xor_checksum(&body[..checksum_start]) == u64::from_str_radix(checksum_hex, 16)?
+        }
+        None => false,
+    };
+
+    Ok(VerifiedResponse { server_timestamp, checksum_ok })
+}
+
+pub fn write_latencies(filename: &str, latencies: &[i64]) -> Result<(), Box<dyn Error>> {
+    let mut out_file = File::create(filename)?;
+    let latencies_bytes: &[u8] = unsafe {
+        std::slice::from_raw_parts(latencies.as_ptr() as *const u8, latencies.len() * 8)
+    };
+    out_file.write_all(latencies_bytes)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_echoed_payload() {
+        let data = BenchmarkData { sizes: vec![5], data_block: b"hello world".to_vec() };
+        let payload = build_payload(&data, 0, true);
+        assert_eq!(payload, b"hello0000000000000062");
+
+        let mut body = payload.clone();
+        body.extend_from_slice(b"1700000000000000000");
+        assert_eq!(
+            verify_response(&body, true).unwrap(),
+            VerifiedResponse { server_timestamp: 1_700_000_000_000_000_000, checksum_ok: true }
+        );
+
+        body[0] = b'j';
+        assert!(!verify_response(&body, true).unwrap().checksum_ok);
+        // Without verification only the timestamp is read.
+        assert!(verify_response(&body, false).unwrap().checksum_ok);
+        assert!(!verify_response(b"1700000000000000000", true).unwrap().checksum_ok);
+        assert!(verify_response(b"170000", false).is_err());
+    }
+}
@@ -1,11 +1,12 @@
 use std::env;
 use std::error::Error;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "../bench_common.rs"]
+mod bench_common;
+use bench_common::{build_payload, get_nanoseconds, read_benchmark_data, verify_response, write_latencies, BenchmarkData};
 
 // Import our library components
-use httprust::{HttpClient, HttpProtocol, HttpMethod, HttpRequest, HttpHeaderView, Http1Protocol, TcpTransport, UnixTransport, Transport};
+use httprust::{HttpClient, HttpMethod, HttpRequest, HttpHeaderView, Http1Protocol, TcpTransport, UnixTransport, Transport};
 
 
 
@@ -14,16 +15,12 @@ struct Config {
     port: u16,
     transport_type: String,
     num_requests: u64,
+    warmup: u64,
     data_file: String,
     output_file: String,
     verify: bool,
     unsafe_res: bool,
-}
-
-#[derive(Debug)]
-struct BenchmarkData {
-    sizes: Vec<u64>,
-    data_block: Vec<u8>,
+    nodelay: bool,
 }
 
 fn parse_args() -> Result<Config, Box<dyn Error>> {
@@ -37,10 +34,12 @@ fn parse_args() -> Result<Config, Box<dyn Error>> {
         port: args[2].parse()?,
         transport_type: "tcp".to_string(),
         num_requests: 1000,
+        warmup: 0,
         data_file: "benchmark_data.bin".to_string(),
         output_file: "latencies_httprust.bin".to_string(),
         verify: true,
         unsafe_res: false,
+        nodelay: true,
     };
 
     let mut i = 3;
@@ -48,39 +47,55 @@ fn parse_args() -> Result<Config, Box<dyn Error>> {
         match args[i].as_str() {
             "--transport" => { config.transport_type = args[i + 1].clone(); i += 2; }
             "--num-requests" => { config.num_requests = args[i + 1].parse()?; i += 2; }
+            "--warmup" => { config.warmup = args[i + 1].parse()?; i += 2; }
             "--data-file" => { config.data_file = args[i + 1].clone(); i += 2; }
             "--output-file" => { config.output_file = args[i + 1].clone(); i += 2; }
             "--no-verify" => { config.verify = false; i += 1; }
             "--unsafe" => { config.unsafe_res = true; i += 1; }
+            "--nodelay" => { config.nodelay = args[i + 1].parse()?; i += 2; }
+            "--nagle" => { config.nodelay = false; i += 1; }
             _ => i += 1,
         }
     }
     Ok(config)
 }
 
-fn read_benchmark_data(filename: &str) -> Result<BenchmarkData, Box<dyn Error>> {
-    let mut file = File::open(filename)?;
-
-    let mut num_requests_buf = [0u8; 8];
-    file.read_exact(&mut num_requests_buf)?;
-    let num_requests = u64::from_le_bytes(num_requests_buf);
-
-    let mut sizes_bytes = vec![0u8; (num_requests as usize) * 8];
-    file.read_exact(&mut sizes_bytes)?;
-    let sizes = sizes_bytes.chunks_exact(8).map(|c| u64::from_le_bytes(c.try_into().unwrap())).collect();
-
-    let mut data_block = Vec::new();
-    file.read_to_end(&mut data_block)?;
-
-    Ok(BenchmarkData { sizes, data_block })
-}
+// Sends request `i` of the benchmark sequence and returns its latency in nanoseconds.
+fn send_request<T: Transport + Default>(
+    client: &mut HttpClient<Http1Protocol<T>>,
+    config: &Config,
+    data: &BenchmarkData,
+    i: u64,
+) -> Result<i64, Box<dyn Error>> {
+    let payload = build_payload(data, i, config.verify);
+
+    let content_len_str = payload.len().to_string();
+    let mut request = HttpRequest {
+        method: HttpMethod::Get, // Will be overridden by post_* call
+        path: "/",
+        body: &payload,
+        headers: vec![HttpHeaderView { key: "Content-Length", value: &content_len_str }],
+    };
 
-fn xor_checksum(data: &[u8]) -> u64 {
-    data.iter().fold(0, |acc, &byte| acc ^ u64::from(byte))
-}
+    let client_receive_time: u64;
+    let verified;
+
+    if config.unsafe_res {
+        let res = client.post_unsafe(&mut request)?;
+        client_receive_time = get_nanoseconds();
+        if res.status_code != 200 { return Err(format!("Request failed with status: {}", res.status_code).into()); }
+        verified = verify_response(res.body, config.verify)?;
+    } else { // Safe response
+        let res = client.post_safe(&mut request)?;
+        client_receive_time = get_nanoseconds();
+        if res.status_code != 200 { return Err(format!("Request failed with status: {}", res.status_code).into()); }
+        verified = verify_response(&res.body, config.verify)?;
+    }
 
-fn get_nanoseconds() -> u64 {
-    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+    if !verified.checksum_ok {
+        eprintln!("Warning: Checksum mismatch on request {}", i);
+    }
+    Ok((client_receive_time - verified.server_timestamp) as i64)
 }
 
 fn run_benchmark<T: Transport + Default>(
@@ -88,88 +103,131 @@ fn run_benchmark<T: Transport + Default>(
     config: &Config,
     data: &BenchmarkData,
     latencies: &mut [i64],
-) -> Result<(), Box<dyn Error>> {
-    for i in 0..config.num_requests {
-        let req_size = data.sizes[i as usize % data.sizes.len()] as usize;
-        let body_slice = &data.data_block[..req_size];
-
-        let mut payload = body_slice.to_vec();
-        if config.verify {
-            let checksum = xor_checksum(body_slice);
-            payload.extend_from_slice(format!("{:016x}", checksum).as_bytes());
-        }
-
-        let content_len_str = payload.len().to_string();
-        let mut request = HttpRequest {
-            method: HttpMethod::Get, // Will be overridden by post_* call
-            path: "/",
-            body: &payload,
-            headers: vec![HttpHeaderView { key: "Content-Length", value: &content_len_str }],
-        };
+) -> Result<u64, Box<dyn Error>> {
+    // Warmup requests absorb connect and slow-start costs; their latencies are discarded.
+    for i in 0..config.warmup {
+        send_request(client, config, data, i)?;
+    }
 
-        let client_receive_time: u64;
-        let server_timestamp: u64;
+    let mut fresh_connection_requests = 0;
 
-        if config.unsafe_res {
-            let res = client.post_unsafe(&mut request)?;
-            client_receive_time = get_nanoseconds();
-            if res.status_code != 200 { return Err(format!("Request failed with status: {}", res.status_code).into()); }
+    for i in 0..config.num_requests {
+        latencies[i as usize] = send_request(client, config, data, i)?;
 
-            if config.verify {
-                let res_payload = &res.body[..res.body.len() - 35];
-                let res_checksum_hex = std::str::from_utf8(&res.body[res.body.len() - 35..res.body.len() - 19])?;
-                if xor_checksum(res_payload) != u64::from_str_radix(res_checksum_hex, 16)? {
-                    eprintln!("Warning: Checksum mismatch on request {}", i);
-                }
-            }
-            let server_timestamp_str = std::str::from_utf8(&res.body[res.body.len() - 19..])?;
-            server_timestamp = server_timestamp_str.parse::<u64>()?;
-        } else { // Safe response
-            let res = client.post_safe(&mut request)?;
-            client_receive_time = get_nanoseconds();
-            if res.status_code != 200 { return Err(format!("Request failed with status: {}", res.status_code).into()); }
-
-            if config.verify {
-                let res_payload = &res.body[..res.body.len() - 35];
-                let res_checksum_hex = std::str::from_utf8(&res.body[res.body.len() - 35..res.body.len() - 19])?;
-                if xor_checksum(res_payload) != u64::from_str_radix(res_checksum_hex, 16)? {
-                    eprintln!("Warning: Checksum mismatch on request {}", i);
-                }
-            }
-            let server_timestamp_str = std::str::from_utf8(&res.body[res.body.len() - 19..])?;
-            server_timestamp = server_timestamp_str.parse::<u64>()?;
+        if !client.last_connection_was_reused() {
+            fresh_connection_requests += 1;
         }
-
-        latencies[i as usize] = (client_receive_time - server_timestamp) as i64;
     }
-    Ok(())
+    Ok(fresh_connection_requests)
 }
 
-
 fn main() -> Result<(), Box<dyn Error>> {
     let config = parse_args()?;
     let data = read_benchmark_data(&config.data_file)?;
     let mut latencies = vec![0i64; config.num_requests as usize];
+    let fresh_connection_requests;
 
     if config.transport_type == "tcp" {
-        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let mut transport = TcpTransport::new();
+        transport.set_nodelay(config.nodelay)?;
+        let mut client = HttpClient::with_protocol(Http1Protocol::new(transport));
         client.connect(&config.host, config.port)?;
-        run_benchmark(&mut client, &config, &data, &mut latencies)?;
+        fresh_connection_requests = run_benchmark(&mut client, &config, &data, &mut latencies)?;
     } else if config.transport_type == "unix" {
         let mut client = HttpClient::<Http1Protocol<UnixTransport>>::new();
         client.connect(&config.host, config.port)?;
-        run_benchmark(&mut client, &config, &data, &mut latencies)?;
+        fresh_connection_requests = run_benchmark(&mut client, &config, &data, &mut latencies)?;
     } else {
         return Err("Unsupported transport type".into());
     }
 
-    let mut out_file = File::create(&config.output_file)?;
-    let latencies_bytes: &[u8] = unsafe {
-        std::slice::from_raw_parts(latencies.as_ptr() as *const u8, latencies.len() * 8)
-    };
-    out_file.write_all(latencies_bytes)?;
+    write_latencies(&config.output_file, &latencies)?;
 
-    println!("httprust_client: completed {} requests.", config.num_requests);
+    if config.transport_type == "tcp" {
+        println!("httprust_client: completed {} requests (nodelay: {}).", config.num_requests, config.nodelay);
+    } else {
+        println!("httprust_client: completed {} requests.", config.num_requests);
+    }
+    println!(
+        "httprust_client: {}/{} requests paid connection setup.",
+        fresh_connection_requests, config.num_requests
+    );
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    // Answers keep-alive POSTs with a bare server timestamp until the client hangs up,
+    // returning how many requests it served.
+    fn spawn_timestamp_server() -> (u16, thread::JoinHandle<u64>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut served = 0;
+            loop {
+                let mut content_length = 0;
+                let mut line = String::new();
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).unwrap() == 0 {
+                        return served;
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    if let Some(value) = line.strip_prefix("Content-Length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let timestamp = format!("{:019}", get_nanoseconds());
+                write!(writer, "HTTP/1.1 200 OK\r\nContent-Length: 19\r\n\r\n{}", timestamp).unwrap();
+                served += 1;
+            }
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn warmup_requests_are_excluded_from_output() {
+        let (port, server) = spawn_timestamp_server();
+        let output = std::env::temp_dir().join(format!("httprust_warmup_{}.bin", std::process::id()));
+        let config = Config {
+            host: "127.0.0.1".to_string(),
+            port,
+            transport_type: "tcp".to_string(),
+            num_requests: 5,
+            warmup: 3,
+            data_file: String::new(),
+            output_file: output.to_str().unwrap().to_string(),
+            verify: false,
+            unsafe_res: false,
+            nodelay: true,
+        };
+        let data = BenchmarkData { sizes: vec![4, 8], data_block: vec![b'x'; 8] };
+        let mut latencies = vec![0i64; config.num_requests as usize];
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect(&config.host, config.port).unwrap();
+        let fresh = run_benchmark(&mut client, &config, &data, &mut latencies).unwrap();
+        drop(client);
+        write_latencies(&config.output_file, &latencies).unwrap();
+
+        assert_eq!(server.join().unwrap(), 8);
+        // The connection was opened during warmup, so no measured request paid for it.
+        assert_eq!(fresh, 0);
+        let written = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(written.len(), 5 * 8);
+    }
+}
@@ -1,8 +1,9 @@
 use std::env;
 use std::error::Error;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::time::{SystemTime, UNIX_EPOCH};
+
+#[path = "../bench_common.rs"]
+mod bench_common;
+use bench_common::{build_payload, get_nanoseconds, read_benchmark_data, verify_response, write_latencies};
 
 struct Config {
     host: String,
@@ -14,12 +15,6 @@ struct Config {
     verify: bool,
 }
 
-#[derive(Debug)]
-struct BenchmarkData {
-    sizes: Vec<u64>,
-    data_block: Vec<u8>,
-}
-
 fn parse_args() -> Result<Config, Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
     if args.len() < 3 {
@@ -65,37 +60,6 @@ fn parse_args() -> Result<Config, Box<dyn Error>> {
     Ok(config)
 }
 
-fn read_benchmark_data(filename: &str) -> Result<BenchmarkData, Box<dyn Error>> {
-    let mut file = File::open(filename)?;
-
-    let mut num_requests_buf = [0u8; 8];
-    file.read_exact(&mut num_requests_buf)?;
-    let num_requests = u64::from_le_bytes(num_requests_buf);
-
-    let mut sizes_bytes = vec![0u8; (num_requests as usize) * 8];
-    file.read_exact(&mut sizes_bytes)?;
-    let sizes = sizes_bytes
-        .chunks_exact(8)
-        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
-        .collect();
-
-    let mut data_block = Vec::new();
-    file.read_to_end(&mut data_block)?;
-
-    Ok(BenchmarkData { sizes, data_block })
-}
-
-fn xor_checksum(data: &[u8]) -> u64 {
-    data.iter().fold(0, |acc, &byte| acc ^ u64::from(byte))
-}
-
-fn get_nanoseconds() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64
-}
-
 fn main() -> Result<(), Box<dyn Error>> {
     let config = parse_args()?;
     let data = read_benchmark_data(&config.data_file)?;
@@ -110,15 +74,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let base_url = format!("http://{}:{}", config.host, config.port);
 
     for i in 0..config.num_requests {
-        let req_size = data.sizes[i as usize % data.sizes.len()] as usize;
-        let body_slice = &data.data_block[..req_size];
-
-        // reqwest requires owned block?
-        let mut payload = body_slice.to_vec();
-        if config.verify {
-            let checksum = xor_checksum(body_slice);
-            payload.extend_from_slice(format!("{:016x}", checksum).as_bytes());
-        }
+        let payload = build_payload(&data, i, config.verify);
 
         let response = client.post(&base_url).body(payload).send()?;
         let client_receive_time = get_nanoseconds();
@@ -127,34 +83,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             return Err(format!("Request failed with status: {}", response.status()).into());
         }
 
-        let body = response.bytes()?.to_vec();
-
-        if config.verify {
-            if body.len() < 35 {
-                eprintln!("Warning: Response body too short on request {}", i);
-            } else {
-                let res_payload = &body[..body.len() - 35];
-                let res_checksum_hex = std::str::from_utf8(&body[body.len() - 35..body.len() - 19])?;
-
-                let calculated = xor_checksum(res_payload);
-                let received = u64::from_str_radix(res_checksum_hex, 16)?;
-
-                if calculated != received {
-                    eprintln!("Warning: Checksum mismatch on request {}", i);
-                }
-            }
+        let body = response.bytes()?;
+        let verified = verify_response(&body, config.verify)?;
+        if !verified.checksum_ok {
+            eprintln!("Warning: Checksum mismatch on request {}", i);
         }
-
-        let server_timestamp_str = std::str::from_utf8(&body[body.len() - 19..])?;
-        let server_timestamp = server_timestamp_str.parse::<u64>()?;
-        latencies[i as usize] = (client_receive_time - server_timestamp) as i64;
+        latencies[i as usize] = (client_receive_time - verified.server_timestamp) as i64;
     }
 
-    let mut out_file = File::create(&config.output_file)?;
-    let latencies_bytes: &[u8] = unsafe {
-        std::slice::from_raw_parts(latencies.as_ptr() as *const u8, latencies.len() * 8)
-    };
-    out_file.write_all(latencies_bytes)?;
+    write_latencies(&config.output_file, &latencies)?;
 
     println!("reqwest_client: completed {} requests.", config.num_requests);
 
@@ -0,0 +1,178 @@
+use crate::http_protocol::{HttpMethod, SafeHttpResponse};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// An in-memory cache of successful GET and HEAD responses, keyed by method and URL.
+// Fresh entries (within `Cache-Control: max-age`) are served without touching the
+// network; stale ones that carry an ETag are revalidated with `If-None-Match`.
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    response: SafeHttpResponse,
+    etag: Option<String>,
+    fresh_until: Instant,
+    policy: CachePolicy,
+}
+
+pub(crate) enum CacheLookup {
+    Fresh(SafeHttpResponse),
+    Stale { etag: String },
+    Miss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CachePolicy {
+    no_store: bool,
+    // Stored, but revalidated before every use.
+    no_cache: bool,
+    max_age: Option<Duration>,
+}
+
+impl CachePolicy {
+    fn of(response: &SafeHttpResponse) -> Option<Self> {
+        let value = response.header("Cache-Control")?;
+        let mut policy = Self { no_store: false, no_cache: false, max_age: None };
+        for directive in value.split(',').map(str::trim) {
+            let (name, argument) = directive.split_once('=').unwrap_or((directive, ""));
+            if name.eq_ignore_ascii_case("no-store") {
+                policy.no_store = true;
+            } else if name.eq_ignore_ascii_case("no-cache") {
+                policy.no_cache = true;
+            } else if name.eq_ignore_ascii_case("max-age") {
+                policy.max_age = argument.trim_matches('"').parse().ok().map(Duration::from_secs);
+            }
+        }
+        Some(policy)
+    }
+
+    fn fresh_until(&self, now: Instant) -> Instant {
+        match self.max_age {
+            Some(max_age) if !self.no_cache => now + max_age,
+            _ => now,
+        }
+    }
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub(crate) fn is_cacheable(method: HttpMethod) -> bool {
+        matches!(method, HttpMethod::Get | HttpMethod::Head)
+    }
+
+    pub(crate) fn lookup(&self, key: &str) -> CacheLookup {
+        match self.entries.get(key) {
+            Some(entry) if Instant::now() < entry.fresh_until => CacheLookup::Fresh(entry.response.clone()),
+            Some(CacheEntry { etag: Some(etag), .. }) => CacheLookup::Stale { etag: etag.clone() },
+            _ => CacheLookup::Miss,
+        }
+    }
+
+    // Only 200s are kept. A response that could never be served again (no max-age and no
+    // ETag to revalidate with) is not worth the memory.
+    pub(crate) fn store(&mut self, key: &str, response: &SafeHttpResponse) {
+        let policy = CachePolicy::of(response).unwrap_or(CachePolicy { no_store: false, no_cache: false, max_age: None });
+        let etag = response.header("ETag").map(str::to_string);
+
+        if response.status_code != 200 || policy.no_store || (policy.max_age.is_none() && etag.is_none()) {
+            self.entries.remove(key);
+            return;
+        }
+
+        let entry = CacheEntry {
+            response: response.clone(),
+            etag,
+            fresh_until: policy.fresh_until(Instant::now()),
+            policy,
+        };
+        self.entries.insert(key.to_string(), entry);
+    }
+
+    // A 304 confirms the stored body. Its own Cache-Control, if any, replaces the stored
+    // one for computing the new freshness lifetime.
+    pub(crate) fn revalidated(&mut self, key: &str, not_modified: &SafeHttpResponse) -> Option<SafeHttpResponse> {
+        let entry = self.entries.get_mut(key)?;
+        if let Some(policy) = CachePolicy::of(not_modified) {
+            if policy.no_store {
+                return self.entries.remove(key).map(|entry| entry.response);
+            }
+            entry.policy = policy;
+        }
+        entry.fresh_until = entry.policy.fresh_until(Instant::now());
+        Some(entry.response.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_protocol::HttpOwnedHeader;
+
+    fn response(status_code: u16, headers: &[(&str, &str)]) -> SafeHttpResponse {
+        SafeHttpResponse {
+            status_code,
+            status_message: String::new(),
+            body: b"cached".to_vec(),
+            headers: headers
+                .iter()
+                .map(|(key, value)| HttpOwnedHeader { key: key.to_string(), value: value.to_string() })
+                .collect(),
+            content_length: Some(6),
+            raw_head: Vec::new(),
+            trailers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_reusable_responses_are_stored() {
+        let mut cache = ResponseCache::new();
+        cache.store("a", &response(200, &[("Cache-Control", "max-age=60")]));
+        cache.store("b", &response(200, &[("ETag", "\"v1\"")]));
+        cache.store("c", &response(200, &[("Cache-Control", "no-store, max-age=60")]));
+        cache.store("d", &response(404, &[("Cache-Control", "max-age=60")]));
+        cache.store("e", &response(200, &[]));
+
+        assert!(matches!(cache.lookup("a"), CacheLookup::Fresh(_)));
+        assert!(matches!(cache.lookup("b"), CacheLookup::Stale { ref etag } if etag == "\"v1\""));
+        for key in ["c", "d", "e"] {
+            assert!(matches!(cache.lookup(key), CacheLookup::Miss));
+        }
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn no_cache_forces_revalidation_despite_max_age() {
+        let mut cache = ResponseCache::new();
+        cache.store("a", &response(200, &[("Cache-Control", "no-cache, max-age=60"), ("ETag", "\"v2\"")]));
+        assert!(matches!(cache.lookup("a"), CacheLookup::Stale { .. }));
+    }
+
+    #[test]
+    fn not_modified_refreshes_entry() {
+        let mut cache = ResponseCache::new();
+        cache.store("a", &response(200, &[("Cache-Control", "max-age=0"), ("ETag", "\"v1\"")]));
+        assert!(matches!(cache.lookup("a"), CacheLookup::Stale { .. }));
+
+        let refreshed = cache.revalidated("a", &response(304, &[("Cache-Control", "max-age=60")])).unwrap();
+        assert_eq!(refreshed.body, b"cached");
+        assert!(matches!(cache.lookup("a"), CacheLookup::Fresh(_)));
+    }
+}
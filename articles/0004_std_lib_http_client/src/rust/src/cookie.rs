@@ -0,0 +1,143 @@
+use crate::error::{Error, HttpClientError, Result};
+use crate::http_protocol::parse_http_date;
+use std::time::SystemTime;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+// One cookie as described by a Set-Cookie header (RFC 6265 5.2). Attributes that were
+// absent, or whose value could not be understood, are left unset, as a user agent ignores them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub expires: Option<SystemTime>,
+    // Seconds; zero or negative means the cookie is already expired.
+    pub max_age: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    // Parses the value of one Set-Cookie header. Only a missing name=value pair or an
+    // empty name is an error; unknown attributes are skipped.
+    pub fn parse(set_cookie_value: &str) -> Result<Self> {
+        let mut pairs = set_cookie_value.split(';');
+        let (name, value) = pairs
+            .next()
+            .and_then(|pair| pair.split_once('='))
+            .ok_or(Error::Http(HttpClientError::HttpParseFailure))?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::Http(HttpClientError::HttpParseFailure));
+        }
+
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: unquote(value.trim()).to_string(),
+            domain: None,
+            path: None,
+            expires: None,
+            max_age: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        };
+
+        for attribute in pairs {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let (key, value) = (key.trim(), unquote(value.trim()));
+
+            if key.eq_ignore_ascii_case("Domain") {
+                // A leading dot is ignored (RFC 6265 5.2.3); domains compare case-insensitively.
+                let domain = value.strip_prefix('.').unwrap_or(value);
+                cookie.domain = (!domain.is_empty()).then(|| domain.to_ascii_lowercase());
+            } else if key.eq_ignore_ascii_case("Path") {
+                cookie.path = value.starts_with('/').then(|| value.to_string());
+            } else if key.eq_ignore_ascii_case("Expires") {
+                cookie.expires = parse_http_date(value);
+            } else if key.eq_ignore_ascii_case("Max-Age") {
+                cookie.max_age = value.parse().ok();
+            } else if key.eq_ignore_ascii_case("Secure") {
+                cookie.secure = true;
+            } else if key.eq_ignore_ascii_case("HttpOnly") {
+                cookie.http_only = true;
+            } else if key.eq_ignore_ascii_case("SameSite") {
+                cookie.same_site = [("Strict", SameSite::Strict), ("Lax", SameSite::Lax), ("None", SameSite::None)]
+                    .into_iter()
+                    .find(|(name, _)| value.eq_ignore_ascii_case(name))
+                    .map(|(_, same_site)| same_site);
+            }
+        }
+
+        Ok(cookie)
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn parses_every_attribute() {
+        let cookie = Cookie::parse(
+            "session=\"abc123\"; domain=.Example.com; Path=/app; Expires=Wed, 21 Oct 2015 07:28:00 GMT; \
+             max-age=3600; SECURE; HttpOnly; SameSite=lax",
+        )
+        .unwrap();
+
+        assert_eq!(
+            cookie,
+            Cookie {
+                name: "session".to_string(),
+                value: "abc123".to_string(),
+                domain: Some("example.com".to_string()),
+                path: Some("/app".to_string()),
+                expires: Some(UNIX_EPOCH + Duration::from_secs(1_445_412_480)),
+                max_age: Some(3600),
+                secure: true,
+                http_only: true,
+                same_site: Some(SameSite::Lax),
+            }
+        );
+    }
+
+    #[test]
+    fn parses_bare_name_value() {
+        let cookie = Cookie::parse("theme=dark").unwrap();
+        assert_eq!((cookie.name.as_str(), cookie.value.as_str()), ("theme", "dark"));
+        assert_eq!((cookie.domain, cookie.path, cookie.expires, cookie.max_age), (None, None, None, None));
+        assert!(!cookie.secure && !cookie.http_only);
+        assert_eq!(cookie.same_site, None);
+
+        // The value may be empty and may itself contain '='.
+        assert_eq!(Cookie::parse("token=a=b").unwrap().value, "a=b");
+        assert_eq!(Cookie::parse("empty=").unwrap().value, "");
+    }
+
+    #[test]
+    fn ignores_attributes_it_cannot_understand() {
+        let cookie = Cookie::parse("id=1; Path=relative; Max-Age=soon; Expires=tomorrow; SameSite=Sometimes; Foo=bar").unwrap();
+        assert_eq!((cookie.path, cookie.max_age, cookie.expires, cookie.same_site), (None, None, None, None));
+        assert_eq!(Cookie::parse("id=1; Max-Age=-1").unwrap().max_age, Some(-1));
+    }
+
+    #[test]
+    fn rejects_missing_name() {
+        for value in ["", "novalue", "=orphan", " ; Path=/"] {
+            assert_eq!(Cookie::parse(value), Err(Error::Http(HttpClientError::HttpParseFailure)), "{value:?}");
+        }
+    }
+}
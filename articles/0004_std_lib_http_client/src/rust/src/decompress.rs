@@ -0,0 +1,461 @@
+use crate::error::{Error, HttpClientError, Result};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DecodedBody {
+    pub body: Vec<u8>,
+    // False when the body came back as received: no encoding, or one we cannot decode.
+    pub decoded: bool,
+}
+
+// Undoes a Content-Encoding list such as "gzip, deflate". Codings are listed in the order
+// they were applied, so they are removed last to first. If any coding in the list is not
+// supported the body is returned untouched, since a partial decode is of no use.
+pub fn decode_content(content_encoding: Option<&str>, body: &[u8]) -> Result<DecodedBody> {
+    let codings: Vec<&str> = content_encoding
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        .collect();
+
+    if codings.is_empty() || !codings.iter().all(|coding| is_supported(coding)) {
+        return Ok(DecodedBody { body: body.to_vec(), decoded: false });
+    }
+
+    let mut decoded = body.to_vec();
+    for coding in codings.iter().rev() {
+        decoded = decode_one(coding, &decoded)?;
+    }
+    Ok(DecodedBody { body: decoded, decoded: true })
+}
+
+// The codings this build can decode, in the form Accept-Encoding lists them.
+pub fn supported_encodings() -> Vec<&'static str> {
+    [("gzip", cfg!(feature = "gzip")), ("deflate", cfg!(feature = "deflate")), ("br", cfg!(feature = "br"))]
+        .into_iter()
+        .filter_map(|(coding, enabled)| enabled.then_some(coding))
+        .collect()
+}
+
+fn is_supported(coding: &str) -> bool {
+    (cfg!(feature = "gzip") && (coding.eq_ignore_ascii_case("gzip") || coding.eq_ignore_ascii_case("x-gzip")))
+        || (cfg!(feature = "deflate") && coding.eq_ignore_ascii_case("deflate"))
+        || (cfg!(feature = "br") && coding.eq_ignore_ascii_case("br"))
+}
+
+fn decode_one(coding: &str, data: &[u8]) -> Result<Vec<u8>> {
+    if coding.eq_ignore_ascii_case("deflate") {
+        // "deflate" is meant to be zlib-wrapped, but enough servers send a bare stream
+        // that it is worth falling back to one.
+        zlib_decode(data).or_else(|_| inflate(data).map(|(out, _)| out))
+    } else if coding.eq_ignore_ascii_case("br") {
+        brotli_decode(data)
+    } else {
+        gzip_decode(data)
+    }
+}
+
+// Brotli's static dictionary alone is some 120 KiB, so unlike gzip and deflate it comes
+// from a crate rather than being decoded here.
+#[cfg(feature = "br")]
+fn brotli_decode(data: &[u8]) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut out = Vec::new();
+    brotli_decompressor::Decompressor::new(data, 4096).read_to_end(&mut out).map_err(|_| decode_failure())?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "br"))]
+fn brotli_decode(_data: &[u8]) -> Result<Vec<u8>> {
+    Err(decode_failure())
+}
+
+fn decode_failure() -> Error {
+    Error::Http(HttpClientError::DecodeFailure)
+}
+
+fn gzip_decode(data: &[u8]) -> Result<Vec<u8>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    let [0x1f, 0x8b, 8, flags, ..] = *data else {
+        return Err(decode_failure());
+    };
+
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let extra = data.get(pos..pos + 2).ok_or_else(decode_failure)?;
+        pos += 2 + usize::from(u16::from_le_bytes([extra[0], extra[1]]));
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let field = data.get(pos..).ok_or_else(decode_failure)?;
+            pos += field.iter().position(|&b| b == 0).ok_or_else(decode_failure)? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    let (out, consumed) = inflate(data.get(pos..).ok_or_else(decode_failure)?)?;
+    let trailer = data.get(pos + consumed..pos + consumed + 8).ok_or_else(decode_failure)?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+
+    if crc != crc32(&out) || size != out.len() as u32 {
+        return Err(decode_failure());
+    }
+    Ok(out)
+}
+
+fn zlib_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let [cmf, flg, ..] = *data else {
+        return Err(decode_failure());
+    };
+    let preset_dictionary = flg & 0x20 != 0;
+    if cmf & 0x0f != 8 || (u16::from(cmf) * 256 + u16::from(flg)) % 31 != 0 || preset_dictionary {
+        return Err(decode_failure());
+    }
+
+    let (out, consumed) = inflate(&data[2..])?;
+    let trailer = data.get(2 + consumed..2 + consumed + 4).ok_or_else(decode_failure)?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&out) {
+        return Err(decode_failure());
+    }
+    Ok(out)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65_521;
+        b = (b + a) % 65_521;
+    }
+    (b << 16) | a
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32> {
+        while self.bit_count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(decode_failure)?;
+            self.pos += 1;
+            self.bit_buf |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1 << n) - 1);
+        self.bit_buf >>= n;
+        self.bit_count -= n;
+        Ok(value)
+    }
+
+    // At most seven bits are ever buffered, so dropping them lands on a byte boundary.
+    fn align_to_byte(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+// Canonical Huffman table: how many codes have each length, and the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(decode_failure());
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(decode_failure())
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// A raw DEFLATE (RFC 1951) decoder. Returns the output and how many input bytes the
+// stream used, so callers can find the trailer of the wrapping format.
+fn inflate(data: &[u8]) -> Result<(Vec<u8>, usize)> {
+    let mut reader = BitReader { data, pos: 0, bit_buf: 0, bit_count: 0 };
+    let mut out = Vec::with_capacity(data.len() * 4);
+
+    loop {
+        let last_block = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = data.get(reader.pos..reader.pos + 4).ok_or_else(decode_failure)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                let nlen = u16::from_le_bytes([header[2], header[3]]);
+                if len != !nlen {
+                    return Err(decode_failure());
+                }
+                let start = reader.pos + 4;
+                let stored = data.get(start..start + usize::from(len)).ok_or_else(decode_failure)?;
+                out.extend_from_slice(stored);
+                reader.pos = start + usize::from(len);
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5u8; 30])?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &literals, &distances)?;
+            }
+            _ => return Err(decode_failure()),
+        }
+
+        if last_block {
+            return Ok((out, reader.pos));
+        }
+    }
+}
+
+fn read_dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_table = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut filled = 0;
+    while filled < lengths.len() {
+        let symbol = code_length_table.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths[..filled].last().ok_or_else(decode_failure)?, 3 + reader.bits(2)? as usize),
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        let run = lengths.get_mut(filled..filled + repeat).ok_or_else(decode_failure)?;
+        run.fill(value);
+        filled += repeat;
+    }
+
+    let literals = Huffman::new(&lengths[..literal_count])?;
+    let distances = Huffman::new(&lengths[literal_count..])?;
+    Ok((literals, distances))
+}
+
+fn inflate_block(reader: &mut BitReader, out: &mut Vec<u8>, literals: &Huffman, distances: &Huffman) -> Result<()> {
+    loop {
+        let symbol = usize::from(literals.decode(reader)?);
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                let extra = *LENGTH_EXTRA.get(index).ok_or_else(decode_failure)?;
+                let length = usize::from(LENGTH_BASE[index]) + reader.bits(u32::from(extra))? as usize;
+
+                let index = usize::from(distances.decode(reader)?);
+                let extra = *DISTANCE_EXTRA.get(index).ok_or_else(decode_failure)?;
+                let distance = usize::from(DISTANCE_BASE[index]) + reader.bits(u32::from(extra))? as usize;
+                if distance > out.len() {
+                    return Err(decode_failure());
+                }
+
+                // Copied a byte at a time because the source may overlap what is being written.
+                let start = out.len() - distance;
+                for i in 0..length {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "br"))]
+    const PANGRAMS: &[u8] = b"The quick brown fox jumps over the lazy dog. Pack my box with five dozen liquor jugs. How vexingly quick daft zebras jump! ";
+
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "br"))]
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn missing_and_unknown_encodings_pass_through() {
+        let identity = decode_content(None, b"plain").unwrap();
+        assert_eq!(identity, DecodedBody { body: b"plain".to_vec(), decoded: false });
+
+        let unknown = decode_content(Some("zstd"), b"opaque").unwrap();
+        assert_eq!(unknown, DecodedBody { body: b"opaque".to_vec(), decoded: false });
+
+        // One unsupported coding anywhere in the list leaves the whole body alone.
+        let mixed = decode_content(Some("deflate, zstd"), b"opaque").unwrap();
+        assert!(!mixed.decoded);
+    }
+
+    #[cfg(feature = "br")]
+    #[test]
+    fn decodes_brotli() {
+        // brotli quality 11, window 22.
+        let body = from_hex(
+            "1b7001408c94abed6841656b6e5abf6169dcf140b223b9343b502707ec3fe0e17ec0b02da79c720edc6491120e85597453d7bd\
+             21b662aa07527882d18d76825dcf65e8fa248a074c60d439b16fe9b0f62e1fb897be2d38f14bb4ae96808da97ed852064d3dec\
+             001c",
+        );
+        let decoded = decode_content(Some("BR"), &body).unwrap();
+        assert!(decoded.decoded);
+        assert_eq!(decoded.body, PANGRAMS.repeat(3));
+
+        assert_eq!(
+            decode_content(Some("br"), &body[..body.len() / 2]).unwrap_err(),
+            Error::Http(HttpClientError::DecodeFailure)
+        );
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn decodes_zlib_wrapped_deflate() {
+        // zlib level 9: a single dynamic-Huffman block.
+        let body = from_hex(
+            "78dae58d4b1683201004afd2b980e7c8320b2f003a20093291af707ae7e5e516aeabba6bde084771cb073a720b307ce25df66f\
+             02578ac882bd1a1d2bdb092f25dedea1456a2e6f30ae92a04101de1d85a36c6d9af0e4864aa70bd6f7fffdaa4cc6201d55fa05\
+             1e986f99be0053858527",
+        );
+        let decoded = decode_content(Some("Deflate"), &body).unwrap();
+        assert!(decoded.decoded);
+        assert_eq!(decoded.body, PANGRAMS.repeat(3));
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn decodes_stored_and_raw_deflate() {
+        let stored = decode_content(Some("deflate"), &from_hex("7801010c00f3ff73746f72656420626c6f636b1f8004bd")).unwrap();
+        assert_eq!(stored.body, b"stored block");
+
+        let raw = decode_content(
+            Some("deflate"),
+            &from_hex("cb48cdc9c957c840225352d372124b5215caf38b72521451a468a40000"),
+        )
+        .unwrap();
+        assert_eq!(raw.body, b"hello hello hello deflate world! ".repeat(4));
+    }
+
+    #[cfg(feature = "deflate")]
+    #[test]
+    fn corrupt_deflate_is_a_decode_failure() {
+        let mut body = from_hex("7801010c00f3ff73746f72656420626c6f636b1f8004bd");
+        let last = body.len() - 1;
+        body[last] ^= 0xff;
+        assert_eq!(
+            decode_content(Some("deflate"), &body[..last - 2]).unwrap_err(),
+            Error::Http(HttpClientError::DecodeFailure)
+        );
+    }
+
+    #[cfg(all(feature = "gzip", feature = "deflate"))]
+    #[test]
+    fn decodes_stacked_gzip_then_deflate() {
+        // gzip was applied first and deflate second, so deflate comes off first.
+        let body = from_hex(
+            "789c93efe660000126666d3d4f9f737ebe811e67bdcff99f3ce3adcfc6f0ff4785b020501200aeb30a85",
+        );
+        let decoded = decode_content(Some("gzip, deflate"), &body).unwrap();
+        assert!(decoded.decoded);
+        assert_eq!(decoded.body, b"stacked encodings");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_trailer_is_verified() {
+        let mut body = from_hex(
+            "1f8b0800000000000203e58d4b1683201004afd2b980e7c8320b2f003a20093291af707ae7e5e516aeabba6bde084771cb07\
+             3a720b307ce25df66f02578ac882bd1a1d2bdb092f25dedea1456a2e6f30ae92a04101de1d85a36c6d9af0e4864aa70bd6f7ff\
+             fdaa4cc6201d55fa051e986f99be00a188e9ab71010000",
+        );
+        assert_eq!(decode_content(Some("gzip"), &body).unwrap().body, PANGRAMS.repeat(3));
+
+        let crc_at = body.len() - 8;
+        body[crc_at] ^= 1;
+        assert_eq!(
+            decode_content(Some("gzip"), &body).unwrap_err(),
+            Error::Http(HttpClientError::DecodeFailure)
+        );
+    }
+}
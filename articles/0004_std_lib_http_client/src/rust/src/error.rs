@@ -15,6 +15,15 @@ pub enum TransportError {
     ConnectionClosed,
     SocketCloseFailure,
     InitFailure,
+    TimedOut,
+    TlsHandshakeFailure,
+    TlsCertificateInvalid,
+    Cancelled,
+    NotConnected,
+    Unsupported,
+    WouldBlock,
+    // A write cut short by a signal before any byte went out; safe to simply try again.
+    Interrupted,
 }
 
 impl fmt::Display for TransportError {
@@ -28,8 +37,25 @@ impl std::error::Error for TransportError {}
 pub enum HttpClientError {
     UrlParseFailure,
     HttpParseFailure,
+    // The status line or a header line could not be parsed. `offset` counts bytes from the
+    // start of the response head and `context` holds the start of the offending line.
+    MalformedStatusLine { offset: usize, context: String },
+    MalformedHeader { offset: usize, context: String },
     InvalidRequest,
     InitFailure,
+    SerializationFailure,
+    DecodeFailure,
+    ProxyAuthenticationRequired,
+    ProxyConnectFailure,
+    UnexpectedStatus(u16),
+    DeadlineExceeded,
+    IntegrityMismatch,
+    FileWriteFailure,
+    FileReadFailure,
+    // The reader supplying a streamed request body failed.
+    BodyReadFailure,
+    PipelineFailure(usize),
+    TooManyHeaders,
 }
 
 impl fmt::Display for HttpClientError {
@@ -53,7 +79,63 @@ impl fmt::Display for Error {
         }
     }
 }
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Http(e) => Some(e),
+        }
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(err: TransportError) -> Self {
+        Error::Transport(err)
+    }
+}
+
+impl From<HttpClientError> for Error {
+    fn from(err: HttpClientError) -> Self {
+        Error::Http(err)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IoPhase {
+    Resolve,
+    Connect,
+    Read,
+    Write,
+    Close,
+}
+
+pub fn classify_io_error(err: &std::io::Error, phase: IoPhase) -> TransportError {
+    use std::io::ErrorKind;
+
+    match (phase, err.kind()) {
+        (IoPhase::Resolve, _) => TransportError::DnsFailure,
+        (_, ErrorKind::TimedOut) => TransportError::TimedOut,
+        (IoPhase::Connect, _) => TransportError::SocketConnectFailure,
+        (IoPhase::Read | IoPhase::Write, ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted) => {
+            TransportError::ConnectionClosed
+        }
+        (IoPhase::Read, ErrorKind::UnexpectedEof) => TransportError::ConnectionClosed,
+        // A blocking socket only reports WouldBlock when its receive timeout expires.
+        (IoPhase::Read, ErrorKind::WouldBlock) => TransportError::TimedOut,
+        (IoPhase::Read, _) => TransportError::SocketReadFailure,
+        (IoPhase::Write, ErrorKind::Interrupted) => TransportError::Interrupted,
+        // A blocking socket only reports WouldBlock when its send timeout expires.
+        (IoPhase::Write, ErrorKind::WouldBlock) => TransportError::TimedOut,
+        (IoPhase::Write, _) => TransportError::SocketWriteFailure,
+        (IoPhase::Close, _) => TransportError::SocketCloseFailure,
+    }
+}
+
+impl Error {
+    pub fn from_io(err: std::io::Error, phase: IoPhase) -> Self {
+        Error::Transport(classify_io_error(&err, phase))
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
@@ -63,9 +145,7 @@ impl From<std::io::Error> for Error {
             std::io::ErrorKind::ConnectionRefused => TransportError::SocketConnectFailure,
             std::io::ErrorKind::ConnectionReset => TransportError::ConnectionClosed,
             std::io::ErrorKind::BrokenPipe => TransportError::SocketWriteFailure,
-            _ if err.to_string().contains("Name or service not known") => {
-                TransportError::DnsFailure
-            }
+            std::io::ErrorKind::WouldBlock => TransportError::WouldBlock,
             _ => TransportError::SocketReadFailure,
         };
         Error::Transport(kind)
@@ -82,4 +162,73 @@ impl From<ParseIntError> for Error {
     fn from(_: ParseIntError) -> Self {
         Error::Http(HttpClientError::HttpParseFailure)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, ErrorKind};
+
+    #[test]
+    fn not_found_maps_by_phase() {
+        let err = io::Error::from(ErrorKind::NotFound);
+        assert_eq!(classify_io_error(&err, IoPhase::Resolve), TransportError::DnsFailure);
+        assert_eq!(classify_io_error(&err, IoPhase::Connect), TransportError::SocketConnectFailure);
+        assert_eq!(classify_io_error(&err, IoPhase::Read), TransportError::SocketReadFailure);
+        assert_eq!(classify_io_error(&err, IoPhase::Write), TransportError::SocketWriteFailure);
+        assert_eq!(classify_io_error(&err, IoPhase::Close), TransportError::SocketCloseFailure);
+    }
+
+    #[test]
+    fn resets_map_to_connection_closed_during_io() {
+        let err = io::Error::from(ErrorKind::ConnectionReset);
+        assert_eq!(classify_io_error(&err, IoPhase::Read), TransportError::ConnectionClosed);
+        assert_eq!(classify_io_error(&err, IoPhase::Write), TransportError::ConnectionClosed);
+        assert_eq!(classify_io_error(&err, IoPhase::Connect), TransportError::SocketConnectFailure);
+    }
+
+    #[test]
+    fn broken_pipe_and_timeouts_map_by_phase() {
+        let broken_pipe = io::Error::from(ErrorKind::BrokenPipe);
+        assert_eq!(classify_io_error(&broken_pipe, IoPhase::Write), TransportError::SocketWriteFailure);
+
+        let refused = io::Error::from(ErrorKind::ConnectionRefused);
+        assert_eq!(classify_io_error(&refused, IoPhase::Connect), TransportError::SocketConnectFailure);
+
+        let timed_out = io::Error::from(ErrorKind::TimedOut);
+        assert_eq!(classify_io_error(&timed_out, IoPhase::Connect), TransportError::TimedOut);
+        assert_eq!(classify_io_error(&timed_out, IoPhase::Read), TransportError::TimedOut);
+    }
+
+    #[test]
+    fn interrupted_and_blocked_writes_are_not_write_failures() {
+        let interrupted = io::Error::from(ErrorKind::Interrupted);
+        assert_eq!(classify_io_error(&interrupted, IoPhase::Write), TransportError::Interrupted);
+        assert_eq!(classify_io_error(&interrupted, IoPhase::Read), TransportError::SocketReadFailure);
+
+        let would_block = io::Error::from(ErrorKind::WouldBlock);
+        assert_eq!(classify_io_error(&would_block, IoPhase::Write), TransportError::TimedOut);
+        assert_eq!(classify_io_error(&would_block, IoPhase::Read), TransportError::TimedOut);
+    }
+
+    #[test]
+    fn conversion_does_not_guess_from_message_text() {
+        // Resolution failures are classified where resolution happens, not from error strings.
+        let err = io::Error::other("Name or service not known");
+        assert_eq!(Error::from(err), Error::Transport(TransportError::SocketReadFailure));
+    }
+
+    #[test]
+    fn source_exposes_inner_error() {
+        use std::error::Error as _;
+
+        let transport = Error::from(TransportError::TimedOut);
+        assert_eq!(transport, Error::Transport(TransportError::TimedOut));
+        let source = transport.source().unwrap();
+        assert_eq!(source.downcast_ref::<TransportError>(), Some(&TransportError::TimedOut));
+
+        let http: Error = HttpClientError::UnexpectedStatus(418).into();
+        let source = http.source().unwrap();
+        assert_eq!(source.downcast_ref::<HttpClientError>(), Some(&HttpClientError::UnexpectedStatus(418)));
+    }
+}
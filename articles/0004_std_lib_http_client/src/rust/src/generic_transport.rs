@@ -0,0 +1,207 @@
+use crate::error::{Error, IoPhase, Result, TransportError};
+use crate::transport::Transport;
+use std::io::{Read, Write};
+
+pub struct GenericTransport<S: Read + Write> {
+    stream: Option<S>,
+}
+
+// Name for callers looking for a `std::io` adapter; it is the same transport.
+pub type StdIoTransport<S> = GenericTransport<S>;
+
+impl<S: Read + Write> GenericTransport<S> {
+    pub fn new(stream: S) -> Self {
+        Self { stream: Some(stream) }
+    }
+
+    pub fn get_ref(&self) -> Option<&S> {
+        self.stream.as_ref()
+    }
+
+    pub fn into_inner(self) -> Option<S> {
+        self.stream
+    }
+}
+
+impl<S: Read + Write> Transport for GenericTransport<S> {
+    fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+        // The wrapped stream arrives already connected; once closed it cannot be reopened.
+        if self.stream.is_some() {
+            Ok(())
+        } else {
+            Err(Error::Transport(TransportError::InitFailure))
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if let Some(stream) = &mut self.stream {
+            let bytes_written = stream.write(buf).map_err(|e| Error::from_io(e, IoPhase::Write))?;
+            if bytes_written == 0 && !buf.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            stream.flush().map_err(|e| Error::from_io(e, IoPhase::Write))?;
+            Ok(bytes_written)
+        } else {
+            Err(Error::Transport(TransportError::SocketWriteFailure))
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if let Some(stream) = &mut self.stream {
+            let bytes_read = stream.read(buf).map_err(|e| Error::from_io(e, IoPhase::Read))?;
+            if bytes_read == 0 && !buf.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            Ok(bytes_read)
+        } else {
+            Err(Error::Transport(TransportError::SocketReadFailure))
+        }
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if let Some(mut stream) = self.stream.take() {
+            stream.flush().map_err(|e| Error::from_io(e, IoPhase::Close))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, TransportError};
+    use crate::http1_protocol::Http1Protocol;
+    use crate::http_protocol::{HttpHeaderView, HttpMethod, HttpProtocol, HttpRequest};
+    use std::cell::RefCell;
+    use std::io::Cursor;
+    use std::rc::Rc;
+
+    struct DuplexStream {
+        input: Cursor<Vec<u8>>,
+        output: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl DuplexStream {
+        fn new(input: &[u8]) -> (Self, Rc<RefCell<Vec<u8>>>) {
+            let output = Rc::new(RefCell::new(Vec::new()));
+            let stream = Self { input: Cursor::new(input.to_vec()), output: Rc::clone(&output) };
+            (stream, output)
+        }
+    }
+
+    impl Read for DuplexStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for DuplexStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn connect_is_a_no_op_while_open() {
+        let (stream, _) = DuplexStream::new(b"");
+        let mut transport = GenericTransport::new(stream);
+        assert!(transport.connect("ignored", 0).is_ok());
+    }
+
+    #[test]
+    fn connect_fails_after_close() {
+        let (stream, _) = DuplexStream::new(b"");
+        let mut transport = GenericTransport::new(stream);
+        transport.close().unwrap();
+
+        let result = transport.connect("ignored", 0);
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::InitFailure));
+    }
+
+    #[test]
+    fn read_reports_connection_closed_at_end_of_stream() {
+        let (stream, _) = DuplexStream::new(b"abc");
+        let mut transport = GenericTransport::new(stream);
+
+        let mut buf = [0u8; 16];
+        assert_eq!(transport.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+        assert_eq!(
+            transport.read(&mut buf).unwrap_err(),
+            Error::Transport(TransportError::ConnectionClosed)
+        );
+    }
+
+    #[test]
+    fn zero_byte_write_reports_connection_closed() {
+        struct FullSink;
+
+        impl Read for FullSink {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Ok(0)
+            }
+        }
+
+        impl Write for FullSink {
+            fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+                Ok(0)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut transport = GenericTransport::new(FullSink);
+        assert_eq!(transport.write(b"").unwrap(), 0);
+        assert_eq!(
+            transport.write(b"GET").unwrap_err(),
+            Error::Transport(TransportError::ConnectionClosed)
+        );
+    }
+
+    #[test]
+    fn close_is_idempotent() {
+        let (stream, _) = DuplexStream::new(b"");
+        let mut transport = GenericTransport::new(stream);
+        assert!(transport.close().is_ok());
+        assert!(transport.close().is_ok());
+        assert!(transport.get_ref().is_none());
+    }
+
+    #[test]
+    fn performs_request_over_wrapped_stream() {
+        let (stream, output) = DuplexStream::new(
+            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello",
+        );
+
+        let mut protocol = Http1Protocol::new(GenericTransport::new(stream));
+        protocol.set_user_agent("");
+        protocol.connect("ignored", 0).unwrap();
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/generic",
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+        };
+
+        let res = protocol.perform_request_unsafe(&request).unwrap();
+
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"hello");
+        assert_eq!(
+            output.borrow().as_slice(),
+            b"GET /generic HTTP/1.1\r\nHost: example.com\r\n\r\n"
+        );
+    }
+}
@@ -1,19 +1,53 @@
-use std::io::Write;
+use std::io::{Read, Write};
 use std::cmp::max;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
 use std::default::Default;
+use std::time::{Duration, Instant};
 
 use crate::error::{Error, HttpClientError, Result, TransportError};
-use crate::http_protocol::{HttpHeaderView, HttpOwnedHeader, HttpMethod, HttpProtocol, HttpRequest, SafeHttpResponse, UnsafeHttpResponse};
+use crate::http_protocol::{HttpHeaderIter, HttpHeaderView, HttpOwnedHeader, HttpMethod, HttpProtocol, HttpRequest, RequestTarget, SafeHttpResponse, UnsafeHttpResponse, DEFAULT_USER_AGENT};
 use crate::transport::Transport;
 
-static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+pub(crate) const DEFAULT_MAX_HEADER_COUNT: usize = 128;
+
+// Client-side breakdown of one request: sending it, waiting for the first response byte,
+// and receiving the rest.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct RequestTimings {
+    pub write: Duration,
+    pub time_to_first_byte: Duration,
+    pub body_read: Duration,
+}
 
 pub struct Http1Protocol<T: Transport> {
     transport: T,
     buffer: Vec<u8>,
     header_size: usize,
     content_length: Option<usize>,
+    body_read_deadline: Option<Duration>,
+    strict_framing: bool,
+    lenient_parsing: bool,
+    requests_on_connection: usize,
+    connection_reusable: bool,
+    expect_body: bool,
+    write_buffering: bool,
+    body_flush_size: Option<usize>,
+    request_deadline: Option<Duration>,
+    deadline_at: Option<Instant>,
+    user_agent: String,
+    max_header_count: usize,
+    auto_shrink: bool,
+    adaptive_buffering: bool,
+    average_response_size: usize,
+    record_timings: bool,
+    first_byte_at: Option<Instant>,
+    timings: RequestTimings,
+    chunked: bool,
+    decoded_body: Vec<u8>,
+    trailer_block: Option<std::ops::Range<usize>>,
+    message_end: Option<usize>,
 }
 
 impl<T: Transport + Default> Default for Http1Protocol<T> {
@@ -23,13 +57,37 @@ impl<T: Transport + Default> Default for Http1Protocol<T> {
             buffer: Vec::new(), // or Vec::default()
             header_size: 0,
             content_length: None,
+            body_read_deadline: None,
+            strict_framing: false,
+            lenient_parsing: false,
+            requests_on_connection: 0,
+            connection_reusable: true,
+            expect_body: true,
+            write_buffering: true,
+            body_flush_size: None,
+            request_deadline: None,
+            deadline_at: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_header_count: DEFAULT_MAX_HEADER_COUNT,
+            auto_shrink: false,
+            adaptive_buffering: false,
+            average_response_size: 0,
+            record_timings: false,
+            first_byte_at: None,
+            timings: RequestTimings::default(),
+            chunked: false,
+            decoded_body: Vec::new(),
+            trailer_block: None,
+            message_end: None,
         }
     }
 }
 
 impl<T: Transport> Http1Protocol<T> {
-    const HEADER_SEPARATOR: &'static [u8] = b"\r\n\r\n";
-    const HEADER_SEPARATOR_CL: &'static [u8] = b"Content-Length:";
+    const UPLOAD_CHUNK_SIZE: usize = 8192;
+    const DOWNLOAD_CHUNK_SIZE: usize = 16384;
+    const MAX_LEADING_EMPTY_LINES: usize = 4;
+    const MIN_RETAINED_CAPACITY: usize = 1024;
 
     pub fn new(transport: T) -> Self {
         Self {
@@ -37,149 +95,771 @@ impl<T: Transport> Http1Protocol<T> {
             buffer: Vec::with_capacity(1024),
             header_size: 0,
             content_length: None,
+            body_read_deadline: None,
+            strict_framing: false,
+            lenient_parsing: false,
+            requests_on_connection: 0,
+            connection_reusable: true,
+            expect_body: true,
+            write_buffering: true,
+            body_flush_size: None,
+            request_deadline: None,
+            deadline_at: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_header_count: DEFAULT_MAX_HEADER_COUNT,
+            auto_shrink: false,
+            adaptive_buffering: false,
+            average_response_size: 0,
+            record_timings: false,
+            first_byte_at: None,
+            timings: RequestTimings::default(),
+            chunked: false,
+            decoded_body: Vec::new(),
+            trailer_block: None,
+            message_end: None,
         }
     }
 
-    // --- Private Helper Methods ---
+    pub fn set_body_read_deadline(&mut self, deadline: Option<Duration>) {
+        self.body_read_deadline = deadline;
+    }
+
+    // Bounds how many header lines a response may carry, so the per-header allocations
+    // stay small whatever the server sends.
+    pub fn set_max_header_count(&mut self, max_header_count: usize) {
+        self.max_header_count = max_header_count;
+    }
+
+    // Releases buffer capacity beyond `capacity`, or beyond the bytes it currently holds
+    // if that is more.
+    pub fn shrink_buffer_to(&mut self, capacity: usize) {
+        self.buffer.shrink_to(capacity);
+    }
+
+    // With auto-shrink on, each request first drops buffer capacity above 4x the previous
+    // response, so one huge response is not held for the life of the connection. Off by
+    // default, since regrowing costs an allocation on workloads that alternate sizes.
+    pub fn set_auto_shrink(&mut self, enabled: bool) {
+        self.auto_shrink = enabled;
+    }
+
+    // With adaptive buffering on, the buffer is sized from a moving average of recent
+    // response sizes: each request starts with room for a typical response, and capacity
+    // left over from a rare large one is released once it exceeds twice the average.
+    pub fn set_adaptive_buffering(&mut self, enabled: bool) {
+        self.adaptive_buffering = enabled;
+    }
+
+    // Off by default to keep clock reads off the hot path.
+    pub fn set_record_timings(&mut self, enabled: bool) {
+        self.record_timings = enabled;
+    }
+
+    // Timings of the last request sent with perform_request_*; all zero when recording is off.
+    pub fn last_timings(&self) -> RequestTimings {
+        self.timings
+    }
 
-    fn build_request_string(&mut self, request: &HttpRequest) {
+    pub fn set_strict_framing(&mut self, strict: bool) {
+        self.strict_framing = strict;
+    }
+
+    // One empty line ahead of the status line is always skipped, as RFC 7230 3.5 asks of
+    // clients. Lenient parsing also skips the few more sent by some buggy servers and load
+    // balancers, and accepts a head the server closed after without its final blank line;
+    // strict parsing rejects both.
+    pub fn set_lenient_parsing(&mut self, lenient: bool) {
+        self.lenient_parsing = lenient;
+    }
+
+    // With buffering off the body is written straight from the caller's slice instead of
+    // being copied behind the header block, at the cost of a second write.
+    pub fn set_write_buffering(&mut self, enabled: bool) {
+        self.write_buffering = enabled;
+    }
+
+    // Caps each body write at `size` bytes. The body is then never copied into the
+    // internal buffer, which stays header-sized however large the upload is.
+    pub fn set_body_flush_size(&mut self, size: Option<usize>) {
+        self.body_flush_size = size.map(|size| size.max(1));
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.transport.is_connected()
+    }
+
+    // Whether the last response left the connection open for another request.
+    pub fn is_connection_reusable(&self) -> bool {
+        self.connection_reusable
+    }
+
+    pub fn reset(&mut self) {
         self.buffer.clear();
+        self.header_size = 0;
+        self.content_length = None;
+        self.chunked = false;
+        self.decoded_body.clear();
+        self.trailer_block = None;
+        self.message_end = None;
+    }
+
+    pub fn last_raw_response(&self) -> &[u8] {
+        &self.buffer
+    }
 
-        let method_str = match request.method {
-            HttpMethod::Get => "GET",
-            HttpMethod::Post => "POST",
+    // The bytes of the last response alone, without anything received after it.
+    pub(crate) fn last_response_bytes(&self) -> &[u8] {
+        if let Some(message_end) = self.message_end {
+            return &self.buffer[..message_end];
+        }
+        let body_len = match (self.expect_body, self.content_length) {
+            (false, _) => 0,
+            (true, Some(len)) => len,
+            (true, None) => self.buffer.len() - self.header_size,
         };
+        &self.buffer[..self.header_size + body_len]
+    }
+
+    // Consumes the protocol and returns the last response's body in the read buffer itself:
+    // the head is shifted out in place, so nothing is allocated or copied out. A chunked
+    // body was already decoded into a buffer of its own, which is handed over instead.
+    pub fn into_body(mut self) -> Vec<u8> {
+        if self.message_end.is_some() {
+            return std::mem::take(&mut self.decoded_body);
+        }
+        let body_end = self.last_response_bytes().len();
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.truncate(body_end);
+        buffer.drain(..self.header_size);
+        buffer
+    }
 
-        write!(&mut self.buffer, "{} {} HTTP/1.1\r\n", method_str, request.path).unwrap();
+    pub fn last_raw_response_preview(&self, max_len: usize) -> String {
+        let shown = &self.buffer[..self.buffer.len().min(max_len)];
+
+        let hex: Vec<String> = shown.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = shown
+            .iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect();
+        let omitted = self.buffer.len() - shown.len();
 
-        for header in &request.headers {
-            write!(&mut self.buffer, "{}: {}\r\n", header.key, header.value).unwrap();
+        if omitted > 0 {
+            format!("{} |{}| (+{} bytes)", hex.join(" "), ascii, omitted)
+        } else {
+            format!("{} |{}|", hex.join(" "), ascii)
         }
+    }
 
-        self.buffer.extend_from_slice(b"\r\n");
+    pub fn send_raw(&mut self, raw_request: &[u8]) -> Result<UnsafeHttpResponse<'_>> {
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = !raw_request.starts_with(b"HEAD ");
+        Self::write_all(&mut self.transport, raw_request)?;
+        self.read_full_response()?;
+        self.parse_unsafe_response()
+    }
 
-        if !request.body.is_empty() && request.method == HttpMethod::Post {
-            self.buffer.extend_from_slice(request.body);
+    // Streams the body from `body` as Transfer-Encoding: chunked, one chunk per read,
+    // for uploads whose length is not known up front.
+    pub fn perform_request_chunked<R: Read>(
+        &mut self,
+        request: &HttpRequest,
+        mut body: R,
+    ) -> Result<UnsafeHttpResponse<'_>> {
+        let has_content_length = request.headers.iter().any(|h| h.key.eq_ignore_ascii_case("Content-Length"));
+        if !request.method.allows_body() || !request.body.is_empty() || has_content_length {
+            return Err(Error::Http(HttpClientError::InvalidRequest));
+        }
+
+        let mut headers = request.headers.clone();
+        if !headers.iter().any(|h| h.key.eq_ignore_ascii_case("Transfer-Encoding")) {
+            headers.push(HttpHeaderView { key: "Transfer-Encoding", value: "chunked" });
+        }
+        let chunked_request = HttpRequest {
+            method: request.method,
+            path: request.path,
+            body: &[],
+            headers,
+        };
+
+        self.build_request_string(&chunked_request)?;
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = true;
+        if let Err(e) = self.send_chunked_body(&mut body) {
+            self.abandon_connection();
+            return Err(e);
         }
+
+        self.read_full_response()?;
+        self.parse_unsafe_response()
     }
 
-    fn read_full_response(&mut self) -> Result<()> {
-        self.buffer.clear();
-        self.header_size = 0;
-        self.content_length = None;
+    // Writes the head already in the buffer, then `body` one chunk per read and the last chunk.
+    fn send_chunked_body<R: Read>(&mut self, body: &mut R) -> Result<()> {
+        Self::write_all(&mut self.transport, &self.buffer)?;
 
+        let mut chunk = vec![0u8; Self::UPLOAD_CHUNK_SIZE];
         loop {
-            let available_capacity = self.buffer.capacity() - self.buffer.len();
-            let read_amount = max(available_capacity, 1024);
-            let old_len = self.buffer.len();
-            self.buffer.resize(old_len + read_amount, 0);
+            let bytes_read = read_body_piece(body, &mut chunk)?;
+            if bytes_read == 0 {
+                break;
+            }
+            self.buffer.clear();
+            write!(&mut self.buffer, "{:x}\r\n", bytes_read).unwrap();
+            self.buffer.extend_from_slice(&chunk[..bytes_read]);
+            self.buffer.extend_from_slice(b"\r\n");
+            Self::write_all(&mut self.transport, &self.buffer)?;
+        }
+        Self::write_all(&mut self.transport, b"0\r\n\r\n")
+    }
+
+    // For a request that failed partway onto the wire: the server is left waiting for the
+    // rest of it, so the connection cannot carry anything else.
+    fn abandon_connection(&mut self) {
+        self.connection_reusable = false;
+        let _ = self.transport.close();
+    }
+
+    // Streams the body from `body` after the head, for uploads not already in memory. With
+    // a known `content_length` exactly that many bytes are read and sent under
+    // Content-Length; without one the body goes out chunked, as in perform_request_chunked.
+    pub fn perform_request_from_reader<R: Read>(
+        &mut self,
+        request: &HttpRequest,
+        body: R,
+        content_length: Option<u64>,
+    ) -> Result<UnsafeHttpResponse<'_>> {
+        let Some(content_length) = content_length else {
+            return self.perform_request_chunked(request, body);
+        };
+        let has_framing_header = request.headers.iter().any(|h| {
+            h.key.eq_ignore_ascii_case("Content-Length") || h.key.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+        if !request.method.allows_body() || !request.body.is_empty() || has_framing_header {
+            return Err(Error::Http(HttpClientError::InvalidRequest));
+        }
+
+        let length_value = content_length.to_string();
+        let mut headers = request.headers.clone();
+        headers.push(HttpHeaderView { key: "Content-Length", value: &length_value });
+        let sized_request = HttpRequest { method: request.method, path: request.path, body: &[], headers };
+
+        self.build_request_string(&sized_request)?;
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = true;
+        if let Err(e) = self.send_sized_body(&mut body.take(content_length), content_length) {
+            self.abandon_connection();
+            return Err(e);
+        }
+
+        self.read_full_response()?;
+        self.parse_unsafe_response()
+    }
+
+    // Writes the head already in the buffer, then exactly `content_length` bytes of `body`.
+    // A reader that runs dry early counts as a failed read.
+    fn send_sized_body<R: Read>(&mut self, body: &mut R, content_length: u64) -> Result<()> {
+        Self::write_all(&mut self.transport, &self.buffer)?;
+
+        let mut remaining = content_length;
+        let mut chunk = vec![0u8; Self::UPLOAD_CHUNK_SIZE];
+        while remaining > 0 {
+            let bytes_read = read_body_piece(body, &mut chunk)?;
+            if bytes_read == 0 {
+                return Err(Error::Http(HttpClientError::BodyReadFailure));
+            }
+            Self::write_all(&mut self.transport, &chunk[..bytes_read])?;
+            remaining -= bytes_read as u64;
+        }
+        Ok(())
+    }
+
+    // Picks up a response whose read stopped with WouldBlock on a non-blocking transport:
+    // what arrived so far is kept, so call this each time the socket turns readable until
+    // it returns something else. Only reads resume; a write that would block leaves the
+    // request partly sent, and the connection has to be dropped.
+    pub fn resume_response(&mut self) -> Result<UnsafeHttpResponse<'_>> {
+        self.continue_response(false, false)?;
+        self.parse_unsafe_response()
+    }
+
+    // POSTs the contents of `file` to `target`. The head is written as usual, then the file
+    // goes to Transport::send_file, so its bytes never pass through this process. A transport
+    // without send_file is turned away before anything is written.
+    pub fn post_file(&mut self, target: &str, file: &Path, headers: &[HttpHeaderView]) -> Result<UnsafeHttpResponse<'_>> {
+        let has_framing_header = headers.iter().any(|h| {
+            h.key.eq_ignore_ascii_case("Content-Length") || h.key.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+        if has_framing_header {
+            return Err(Error::Http(HttpClientError::InvalidRequest));
+        }
+
+        let file = File::open(file).map_err(|_| Error::Http(HttpClientError::FileReadFailure))?;
+        let file_len = file.metadata().map_err(|_| Error::Http(HttpClientError::FileReadFailure))?.len();
+        let content_length = file_len.to_string();
+        if !self.transport.supports_send_file() {
+            return Err(Error::Transport(TransportError::Unsupported));
+        }
+
+        let mut request_headers = headers.to_vec();
+        request_headers.push(HttpHeaderView { key: "Content-Length", value: &content_length });
+        let request = HttpRequest { method: HttpMethod::Post, path: target, body: &[], headers: request_headers };
+
+        self.build_request_string(&request)?;
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = true;
+        let sent = Self::write_all(&mut self.transport, &self.buffer)
+            .and_then(|()| self.transport.send_file(file.as_raw_fd(), 0, file_len));
+        // Once any of the request is out, a failure leaves the server expecting the rest of
+        // a body that will never come, so the connection is closed.
+        if let Err(e) = sent {
+            self.connection_reusable = false;
+            let _ = self.transport.close();
+            return Err(e);
+        }
 
-            let bytes_read = match self.transport.read(&mut self.buffer[old_len..]) {
+        self.read_full_response()?;
+        self.parse_unsafe_response()
+    }
+
+    // Like perform_request_safe, but the body is written to `sink` as it arrives instead of
+    // being held in memory. The returned response has an empty body; the count is the number
+    // of body bytes handed to `sink`.
+    pub fn perform_request_to_writer<W: Write>(
+        &mut self,
+        request: &HttpRequest,
+        sink: &mut W,
+    ) -> Result<(SafeHttpResponse, u64)> {
+        self.build_request_string(request)?;
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = !matches!(request.method, HttpMethod::Head | HttpMethod::Connect);
+        self.flush_request(request)?;
+
+        self.reset();
+        let mut chunk = vec![0u8; Self::DOWNLOAD_CHUNK_SIZE];
+        while self.header_size == 0 {
+            self.check_deadline()?;
+            let bytes_read = match self.transport.read(&mut chunk) {
                 Ok(n) => n,
-                Err(Error::Transport(TransportError::ConnectionClosed)) => {
-                    self.buffer.truncate(old_len);
-                    if self.content_length.is_some() && self.buffer.len() < self.header_size + self.content_length.unwrap() {
-                        return Err(Error::Http(HttpClientError::HttpParseFailure));
-                    }
-                    break;
-                }
-                Err(e) => {
-                    self.buffer.truncate(old_len);
-                    return Err(e);
+                Err(Error::Transport(TransportError::ConnectionClosed)) if !self.buffer.is_empty() => {
+                    return Err(Error::Http(HttpClientError::HttpParseFailure));
                 }
+                Err(e) => return Err(e),
             };
+            self.buffer.extend_from_slice(&chunk[..bytes_read]);
+            if let Some(header_end) = find_header_end(&self.buffer) {
+                self.record_header_end(header_end)?;
+            }
+        }
 
-            self.buffer.truncate(old_len + bytes_read);
+        let mut head = into_safe_response(parse_response_view(
+            &self.buffer,
+            self.header_size,
+            self.content_length,
+            false,
+            self.max_header_count,
+        )?);
+        if !self.expect_body {
+            return Ok((head, 0));
+        }
 
-            if self.header_size == 0 {
-                if let Some(pos) = self.buffer.windows(4).position(|window| window == Self::HEADER_SEPARATOR) {
-                    self.header_size = pos + 4;
-                    let headers_view = &self.buffer[..self.header_size];
-
-                    for line in headers_view.split(|&b| b == b'\n').skip(1) {
-                        let line = if line.ends_with(b"\r") { &line[..line.len() - 1] } else { line };
-                        if line.is_empty() { break; }
-
-                        if line.len() >= 15 && line[..15].eq_ignore_ascii_case(Self::HEADER_SEPARATOR_CL) {
-                            if let Some(colon_pos) = line.iter().position(|&b| b == b':') {
-                                let value_slice = &line[colon_pos + 1..];
-                                if let Some(start) = value_slice.iter().position(|&b| !b.is_ascii_whitespace()) {
-                                    if let Ok(s) = std::str::from_utf8(&value_slice[start..]) {
-                                        if let Ok(len) = s.parse::<usize>() {
-                                            self.content_length = Some(len);
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
+        if self.chunked {
+            let mut decoder = ChunkedStreamDecoder::default();
+            let mut pending = self.buffer.split_off(self.header_size);
+            let mut written = decoder.feed(&mut pending, sink)?;
+            while !decoder.is_finished() {
+                self.check_deadline()?;
+                let bytes_read = match self.transport.read(&mut chunk) {
+                    Ok(n) => n,
+                    Err(Error::Transport(TransportError::ConnectionClosed)) => {
+                        return Err(Error::Http(HttpClientError::HttpParseFailure));
                     }
-                }
+                    Err(e) => return Err(e),
+                };
+                pending.extend_from_slice(&chunk[..bytes_read]);
+                written += decoder.feed(&mut pending, sink)?;
             }
+            head.trailers = owned_headers(HttpHeaderIter::new(&decoder.trailers));
+            head.headers.extend(head.trailers.iter().cloned());
+            return Ok((head, written as u64));
+        }
 
-            if let Some(content_len) = self.content_length {
-                if self.buffer.len() >= self.header_size + content_len {
-                    break;
+        let write_failure = |_| Error::Http(HttpClientError::FileWriteFailure);
+        let body_limit = self.content_length.unwrap_or(usize::MAX);
+
+        // Body bytes that arrived with the headers go out first.
+        let received = &self.buffer[self.header_size..];
+        let received = &received[..received.len().min(body_limit)];
+        sink.write_all(received).map_err(write_failure)?;
+        let mut written = received.len();
+
+        while written < body_limit {
+            self.check_deadline()?;
+            let wanted = chunk.len().min(body_limit - written);
+            match self.transport.read(&mut chunk[..wanted]) {
+                Ok(n) => {
+                    sink.write_all(&chunk[..n]).map_err(write_failure)?;
+                    written += n;
+                }
+                Err(Error::Transport(TransportError::ConnectionClosed)) if self.content_length.is_none() => break,
+                Err(Error::Transport(TransportError::ConnectionClosed)) => {
+                    return Err(Error::Http(HttpClientError::HttpParseFailure));
                 }
+                Err(e) => return Err(e),
             }
         }
 
-        if self.header_size == 0 && !self.buffer.is_empty() {
-            return Err(Error::Http(HttpClientError::HttpParseFailure));
+        Ok((head, written as u64))
+    }
+
+    // Writes every request before reading any response. Responses come back as owned
+    // copies, since each one is parsed out of the same internal buffer.
+    pub fn pipeline(&mut self, requests: &[HttpRequest]) -> Result<Vec<SafeHttpResponse>> {
+        // Every request is built before the first is written, so one rejected late in the
+        // batch cannot leave those ahead of it on the wire with nobody reading the answers.
+        let mut wire = Vec::new();
+        for request in requests {
+            write_request_head(&mut wire, request, &self.user_agent)?;
+            if !request.body.is_empty() && request.method.allows_body() {
+                wire.extend_from_slice(request.body);
+            }
+        }
+
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += requests.len();
+
+        // Once anything is sent, a failure leaves responses unread on the connection.
+        let result = Self::write_all(&mut self.transport, &wire).and_then(|()| self.read_pipelined_responses(requests));
+        if result.is_err() {
+            self.abandon_connection();
+        }
+        result
+    }
+
+    fn read_pipelined_responses(&mut self, requests: &[HttpRequest]) -> Result<Vec<SafeHttpResponse>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        let mut retained = Vec::new();
+
+        // Parse failures and early closes are reported against the response they hit,
+        // since everything after it on the wire is then unrecoverable.
+        let framing_failure = |i: usize| {
+            move |e: Error| match e {
+                Error::Http(HttpClientError::HttpParseFailure)
+                | Error::Transport(TransportError::ConnectionClosed) => Error::Http(HttpClientError::PipelineFailure(i)),
+                e => e,
+            }
+        };
+
+        for (i, request) in requests.iter().enumerate() {
+            let is_last = i + 1 == requests.len();
+            self.expect_body = !matches!(request.method, HttpMethod::Head | HttpMethod::Connect);
+            self.read_response(&retained, !is_last).map_err(framing_failure(i))?;
+
+            // Reading to EOF is only a valid frame for the final response. Chunked bodies
+            // carry their own end, so they are fine anywhere.
+            if !is_last && self.expect_body && self.content_length.is_none() && !self.chunked {
+                return Err(Error::Http(HttpClientError::PipelineFailure(i)));
+            }
+            responses.push(into_safe_response(self.parse_unsafe_response().map_err(framing_failure(i))?));
+
+            let body_len = if !self.expect_body {
+                0
+            } else {
+                self.content_length.unwrap_or(self.buffer.len() - self.header_size)
+            };
+            let message_end = self.message_end.unwrap_or(self.header_size + body_len);
+            retained = self.buffer[message_end..].to_vec();
+        }
+
+        Ok(responses)
+    }
+
+    // --- Private Helper Methods ---
+
+    // Reports a missing connect before anything is written, instead of as whatever the
+    // first write returns. Invalid requests are still rejected first.
+    fn ensure_connected(&self) -> Result<()> {
+        if self.transport.is_connected() {
+            Ok(())
+        } else {
+            Err(Error::Transport(TransportError::NotConnected))
+        }
+    }
+
+    fn build_request_string(&mut self, request: &HttpRequest) -> Result<()> {
+        let previous_len = self.buffer.len();
+        self.buffer.clear();
+        if self.auto_shrink {
+            let retained = previous_len.max(Self::MIN_RETAINED_CAPACITY);
+            if self.buffer.capacity() > 4 * retained {
+                self.buffer.shrink_to(retained);
+            }
+        }
+        if self.adaptive_buffering && self.average_response_size != 0 {
+            let target = self.average_response_size.max(Self::MIN_RETAINED_CAPACITY);
+            if self.buffer.capacity() > 2 * target {
+                self.buffer.shrink_to(target);
+            } else {
+                self.buffer.reserve(target);
+            }
+        }
+        write_request_head(&mut self.buffer, request, &self.user_agent)?;
+
+        if self.buffers_body() && !request.body.is_empty() && request.method.allows_body() {
+            self.buffer.extend_from_slice(request.body);
         }
 
         Ok(())
     }
 
-    fn parse_unsafe_response<'a>(&'a self) -> Result<UnsafeHttpResponse<'a>> {
-        if self.header_size == 0 {
-            return Err(Error::Http(HttpClientError::HttpParseFailure));
+    fn flush_request(&mut self, request: &HttpRequest) -> Result<()> {
+        Self::write_all(&mut self.transport, &self.buffer)?;
+
+        if !self.buffers_body() && !request.body.is_empty() && request.method.allows_body() {
+            let flush_size = self.body_flush_size.unwrap_or(request.body.len());
+            for piece in request.body.chunks(flush_size) {
+                Self::write_all(&mut self.transport, piece)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_deadline(&mut self) {
+        self.deadline_at = self.request_deadline.map(|deadline| Instant::now() + deadline);
+    }
+
+    fn buffers_body(&self) -> bool {
+        self.write_buffering && self.body_flush_size.is_none()
+    }
+
+    fn write_all(transport: &mut T, mut bytes: &[u8]) -> Result<()> {
+        while !bytes.is_empty() {
+            let bytes_written = match transport.write(bytes) {
+                Ok(n) => n,
+                Err(Error::Transport(TransportError::Interrupted)) => continue,
+                Err(e) => return Err(e),
+            };
+            // No progress on a non-empty buffer means the peer is gone; looping would spin.
+            if bytes_written == 0 {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            bytes = &bytes[bytes_written..];
         }
+        Ok(())
+    }
+
+    fn read_full_response(&mut self) -> Result<()> {
+        self.read_response(&[], false)
+    }
+
+    // Bytes already received for this response (e.g. trailing bytes from a pipelined
+    // predecessor) are parsed before anything more is read from the transport.
+    fn read_response(&mut self, retained: &[u8], allow_trailing: bool) -> Result<()> {
+        self.reset();
+        self.buffer.extend_from_slice(retained);
+        let skip_read = !self.buffer.is_empty();
+        self.continue_response(skip_read, allow_trailing)
+    }
+
+    // The read loop proper. Every bit of progress lives in self, so a read that fails with
+    // WouldBlock can be picked up again by calling this once more.
+    fn continue_response(&mut self, mut skip_read: bool, allow_trailing: bool) -> Result<()> {
+        let mut body_started_at: Option<Instant> = None;
+        let mut leading_lines_skipped = 0;
+
+        loop {
+            self.check_deadline()?;
+
+            if !skip_read {
+                let old_len = self.buffer.len();
+                // Once Content-Length is known the buffer is sized for the whole response in
+                // one step and the body is read straight into place; unframed responses keep
+                // growing until the peer closes.
+                let read_end = match self.content_length {
+                    Some(content_len)
+                        if self.header_size != 0
+                            && self.expect_body
+                            && old_len < self.header_size + content_len =>
+                    {
+                        self.header_size + content_len
+                    }
+                    _ => old_len + max(self.buffer.capacity() - old_len, 1024),
+                };
+                self.buffer.reserve_exact(read_end - old_len);
+                self.buffer.resize(read_end, 0);
+
+                let bytes_read = match self.transport.read(&mut self.buffer[old_len..]) {
+                    Ok(n) => n,
+                    Err(Error::Transport(TransportError::ConnectionClosed)) => {
+                        self.buffer.truncate(old_len);
+                        // Closed before a single byte of response: the request went unanswered.
+                        if self.buffer.is_empty() {
+                            return Err(Error::Transport(TransportError::ConnectionClosed));
+                        }
+                        // A head that stops after a complete line, without the blank line that
+                        // ends it, is incomplete and rejected below. Lenient parsing instead
+                        // takes the close as the end of the head; the body is then empty.
+                        if self.header_size == 0 && self.lenient_parsing && self.buffer.ends_with(b"\n") {
+                            let line_end: &[u8] = if self.buffer.ends_with(b"\r\n") { b"\r\n" } else { b"\n" };
+                            self.buffer.extend_from_slice(line_end);
+                            self.record_header_end(self.buffer.len())?;
+                        }
+                        // The peer may close (or reset) in the same instant the final body byte
+                        // lands, so only a genuinely short body is treated as a failure here.
+                        if let Some(content_len) = self.content_length
+                            && self.buffer.len() < self.header_size + content_len
+                        {
+                            return Err(Error::Http(HttpClientError::HttpParseFailure));
+                        }
+                        // A chunked body is only complete once its last chunk is seen, and
+                        // that ends the loop before any further read.
+                        if self.chunked && self.expect_body {
+                            return Err(Error::Http(HttpClientError::HttpParseFailure));
+                        }
+                        break;
+                    }
+                    Err(e) => {
+                        self.buffer.truncate(old_len);
+                        return Err(e);
+                    }
+                };
 
-        let headers_block = &self.buffer[..self.header_size - Self::HEADER_SEPARATOR.len()];
+                self.buffer.truncate(old_len + bytes_read);
+                if self.record_timings && self.first_byte_at.is_none() && bytes_read > 0 {
+                    self.first_byte_at = Some(Instant::now());
+                }
+            }
+            skip_read = false;
 
-        let mut parts = headers_block.splitn(2, |&b| b == b'\n');
-        let status_line_bytes = parts.next().unwrap_or_default();
-        let rest_of_headers_bytes = parts.next().unwrap_or_default();
+            if self.header_size == 0 {
+                let limit = if self.lenient_parsing { Self::MAX_LEADING_EMPTY_LINES } else { 1 };
+                skip_leading_empty_lines(&mut self.buffer, &mut leading_lines_skipped, limit)?;
+            }
 
-        let status_line_str = std::str::from_utf8(status_line_bytes)?.trim_end();
-        let mut status_parts = status_line_str.splitn(3, ' ');
+            if self.header_size == 0
+                && let Some(header_end) = find_header_end(&self.buffer)
+            {
+                self.record_header_end(header_end)?;
+            }
 
-        let _http_version = status_parts.next();
-        let status_code_str = status_parts.next().ok_or(Error::Http(HttpClientError::HttpParseFailure))?;
-        let status_message = status_parts.next().unwrap_or("");
-        let status_code = status_code_str.parse::<u16>()?;
+            // Responses to HEAD and CONNECT, and bodyless statuses, never carry a body,
+            // whatever Content-Length claims.
+            if self.header_size != 0 && !self.expect_body {
+                break;
+            }
 
-        let headers = rest_of_headers_bytes
-            .split(|&b| b == b'\n')
-            .filter_map(|line| {
-                let line = if line.ends_with(b"\r") { &line[..line.len() - 1] } else { line };
-                if line.is_empty() { return None; }
+            if self.header_size != 0
+                && self.chunked
+                && let Some(layout) = scan_chunked(&self.buffer[self.header_size..])?
+            {
+                self.decode_chunked(layout);
+                break;
+            }
 
-                let mut parts = line.splitn(2, |&b| b == b':');
-                let key_bytes = parts.next()?;
-                let value_bytes = parts.next()?;
+            if let Some(content_len) = self.content_length
+                && self.buffer.len() >= self.header_size + content_len
+            {
+                // Reads stop at the body's end, so surplus bytes sent in a later segment
+                // are still queued on the transport; check for those too.
+                if self.strict_framing
+                    && !allow_trailing
+                    && (self.buffer.len() > self.header_size + content_len || self.transport.input_pending())
+                {
+                    return Err(Error::Http(HttpClientError::HttpParseFailure));
+                }
+                break;
+            }
 
-                let key = std::str::from_utf8(key_bytes).ok()?;
-                let value = std::str::from_utf8(value_bytes).ok()?.trim();
+            // Bounds the total body transfer time, so a peer trickling bytes just fast
+            // enough to dodge any per-read timeout cannot hold the request open forever.
+            if let Some(deadline) = self.body_read_deadline
+                && self.header_size != 0
+                && self.buffer.len() > self.header_size
+            {
+                let started_at = *body_started_at.get_or_insert_with(Instant::now);
+                if started_at.elapsed() > deadline {
+                    return Err(Error::Transport(TransportError::TimedOut));
+                }
+            }
+        }
 
-                Some(HttpHeaderView { key, value })
-            })
-            .collect();
+        if self.header_size == 0 && !self.buffer.is_empty() {
+            return Err(Error::Http(HttpClientError::HttpParseFailure));
+        }
 
-        let body = if let Some(len) = self.content_length {
-            &self.buffer[self.header_size..self.header_size + len]
+        if self.adaptive_buffering {
+            self.observe_response_size(self.buffer.len());
+        }
+        Ok(())
+    }
+
+    // Exponential moving average weighted 1/8 towards the newest response, so one outlier
+    // moves the target only a little and steady sizes converge on it exactly.
+    fn observe_response_size(&mut self, size: usize) {
+        self.average_response_size = if self.average_response_size == 0 {
+            size
         } else {
-            &self.buffer[self.header_size..]
+            self.average_response_size - self.average_response_size / 8 + size / 8
         };
+    }
 
-        Ok(UnsafeHttpResponse {
-            status_code,
-            status_message,
-            headers,
-            body,
-            content_length: self.content_length,
-        })
+    fn record_header_end(&mut self, header_end: usize) -> Result<()> {
+        self.header_size = header_end;
+        let headers_view = &self.buffer[..self.header_size];
+
+        // 1xx, 204 and 304 responses never carry a body (RFC 7230 3.3.3), so a
+        // Content-Length sent alongside them must not make us wait for bytes.
+        if status_forbids_body(headers_view) {
+            self.expect_body = false;
+        }
+        self.connection_reusable = connection_reusable(headers_view);
+
+        // Transfer-Encoding wins over any Content-Length sent alongside it (RFC 7230 3.3.3).
+        self.chunked = is_chunked(headers_view);
+        self.content_length = if self.chunked { None } else { content_length_of(headers_view)? };
+        Ok(())
+    }
+
+    // Gathers the chunk data of a complete chunked message into decoded_body. The buffer
+    // itself is left as received, so the raw response stays byte for byte what was sent.
+    fn decode_chunked(&mut self, layout: ChunkedLayout) {
+        let body = &self.buffer[self.header_size..];
+        for chunk in &layout.chunks {
+            self.decoded_body.extend_from_slice(&body[chunk.clone()]);
+        }
+        self.trailer_block = Some(self.header_size + layout.trailers.start..self.header_size + layout.trailers.end);
+        self.message_end = Some(self.header_size + layout.end);
+    }
+
+    fn check_deadline(&self) -> Result<()> {
+        match self.deadline_at {
+            Some(deadline_at) if Instant::now() > deadline_at => Err(Error::Http(HttpClientError::DeadlineExceeded)),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_unsafe_response<'a>(&'a self) -> Result<UnsafeHttpResponse<'a>> {
+        if let Some(trailers) = &self.trailer_block {
+            return chunked_response_view(
+                &self.buffer,
+                self.header_size,
+                &self.decoded_body,
+                trailers.clone(),
+                self.max_header_count,
+            );
+        }
+        parse_response_view(&self.buffer, self.header_size, self.content_length, self.expect_body, self.max_header_count)
     }
 
     #[allow(dead_code)] // To silence warnings until we use it in all tests
@@ -197,71 +877,547 @@ impl<T: Transport> Http1Protocol<T> {
 impl<T: Transport> HttpProtocol for Http1Protocol<T> {
     type Transport = T;
     fn connect(&mut self, host: &str, port: u16) -> Result<()> {
-        self.transport.connect(host, port)
+        self.transport.connect(host, port)?;
+        self.requests_on_connection = 0;
+        self.connection_reusable = true;
+        Ok(())
     }
 
     fn disconnect(&mut self) -> Result<()> {
+        self.requests_on_connection = 0;
         self.transport.close()
     }
 
-    fn perform_request_unsafe<'a, 'b>(&'a mut self, request: &'b HttpRequest) -> Result<UnsafeHttpResponse<'a>> {
-        self.build_request_string(request);
-        self.transport.write(&self.buffer)?;
+    fn last_connection_was_reused(&self) -> bool {
+        self.requests_on_connection > 1
+    }
+
+    fn set_request_deadline(&mut self, deadline: Option<Duration>) {
+        self.request_deadline = deadline;
+    }
+
+    fn set_user_agent(&mut self, user_agent: &str) {
+        self.user_agent = user_agent.to_string();
+    }
+
+    fn perform_request_unsafe<'a>(&'a mut self, request: &HttpRequest) -> Result<UnsafeHttpResponse<'a>> {
+        self.build_request_string(request)?;
+        self.ensure_connected()?;
+        self.start_deadline();
+        self.requests_on_connection += 1;
+        self.expect_body = !matches!(request.method, HttpMethod::Head | HttpMethod::Connect);
+
+        if !self.record_timings {
+            self.flush_request(request)?;
+            self.read_full_response()?;
+            return self.parse_unsafe_response();
+        }
+
+        self.first_byte_at = None;
+        let started = Instant::now();
+        self.flush_request(request)?;
+        let written = Instant::now();
         self.read_full_response()?;
+        let finished = Instant::now();
+
+        let first_byte_at = self.first_byte_at.unwrap_or(finished);
+        self.timings = RequestTimings {
+            write: written - started,
+            time_to_first_byte: first_byte_at.saturating_duration_since(written),
+            body_read: finished - first_byte_at,
+        };
         self.parse_unsafe_response()
     }
 
-    fn perform_request_safe<'a>(&mut self, request: &'a HttpRequest) -> Result<SafeHttpResponse> {
+    fn perform_request_safe(&mut self, request: &HttpRequest) -> Result<SafeHttpResponse> {
         let unsafe_res = self.perform_request_unsafe(request)?;
+        Ok(into_safe_response(unsafe_res))
+    }
 
-        let headers = unsafe_res.headers
-            .iter()
-            .map(|h| HttpOwnedHeader {
-                key: h.key.to_string(),
-                value: h.value.to_string(),
-            })
-            .collect();
-
-        Ok(SafeHttpResponse {
-            status_code: unsafe_res.status_code,
-            status_message: unsafe_res.status_message.to_string(),
-            body: unsafe_res.body.to_vec(),
-            headers,
-            content_length: unsafe_res.content_length,
-        })
+    fn into_transport(self) -> T {
+        self.transport
     }
 }
 
+const HEADER_SEPARATOR_CL: &[u8] = b"Content-Length:";
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::net::{TcpListener, Shutdown};
-    use std::os::unix::net::{UnixListener, UnixStream};
-    use std::io::{Read, Write};
-    use std::thread;
-    use std::sync::mpsc;
+pub(crate) fn write_request_head(buffer: &mut Vec<u8>, request: &HttpRequest, user_agent: &str) -> Result<()> {
+    let target = RequestTarget::from_path(request.path);
+    if !target.is_valid_for(request.method) {
+        return Err(Error::Http(HttpClientError::InvalidRequest));
+    }
 
-    use crate::transport::Transport;
-    use crate::tcp_transport::TcpTransport;
-    use crate::unix_transport::UnixTransport;
+    // Anything that could end a line early would let a caller splice in extra
+    // headers or a second request.
+    let has_line_break = |s: &str| s.bytes().any(|b| b == b'\r' || b == b'\n');
+    if request.path.bytes().any(|b| b.is_ascii_control())
+        || request.headers.iter().any(|h| has_line_break(h.key) || has_line_break(h.value))
+    {
+        return Err(Error::Http(HttpClientError::InvalidRequest));
+    }
 
-    macro_rules! generate_http1_protocol_tests {
-        ($transport_type:ty, $server_logic:expr) => {
-            #[test]
-            fn connect_and_disconnect_succeeds() {
-                let server_handle = $server_logic(|_stream| {});
-                let mut protocol = Http1Protocol::new(<$transport_type>::new());
-                assert!(protocol.connect(&server_handle.addr, server_handle.port).is_ok());
-                assert!(protocol.disconnect().is_ok());
-            }
+    write!(buffer, "{} {} HTTP/1.1\r\n", request.method.as_str(), target.as_str()).unwrap();
 
-            #[test]
-            fn perform_request_fails_if_not_connected() {
-                let mut protocol = Http1Protocol::new(<$transport_type>::new());
-                let request = HttpRequest {
-                    method: HttpMethod::Get,
-                    path: "/",
+    // Caller headers go out exactly as given: same order, duplicates kept, nothing merged.
+    for header in &request.headers {
+        write!(buffer, "{}: {}\r\n", header.key, header.value).unwrap();
+    }
+
+    let has_user_agent = request.headers.iter().any(|h| h.key.eq_ignore_ascii_case("User-Agent"));
+    if !user_agent.is_empty() && !has_user_agent {
+        write!(buffer, "User-Agent: {}\r\n", user_agent).unwrap();
+    }
+
+    let send_body = !request.body.is_empty() && request.method.allows_body();
+
+    if send_body {
+        let has_framing_header = request.headers.iter().any(|h| {
+            h.key.eq_ignore_ascii_case("Content-Length")
+                || h.key.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+        if !has_framing_header {
+            write!(buffer, "Content-Length: {}\r\n", request.body.len()).unwrap();
+        }
+    }
+
+    buffer.extend_from_slice(b"\r\n");
+    Ok(())
+}
+
+// Reads the next piece of a streamed request body. A read interrupted by a signal is
+// simply retried; any other failure is reported as the reader's.
+fn read_body_piece<R: Read>(body: &mut R, chunk: &mut [u8]) -> Result<usize> {
+    loop {
+        match body.read(chunk) {
+            Ok(n) => return Ok(n),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return Err(Error::Http(HttpClientError::BodyReadFailure)),
+        }
+    }
+}
+
+pub(crate) fn content_length_of(headers_view: &[u8]) -> Result<Option<usize>> {
+    let mut line_start = 0;
+    for raw_line in headers_view.split(|&b| b == b'\n') {
+        let offset = line_start;
+        line_start += raw_line.len() + 1;
+        if offset == 0 { continue; }
+
+        let line = raw_line.strip_suffix(b"\r").unwrap_or(raw_line);
+        if line.is_empty() { break; }
+
+        if line.len() >= 15 && line[..15].eq_ignore_ascii_case(HEADER_SEPARATOR_CL)
+            && let Some(colon_pos) = line.iter().position(|&b| b == b':')
+        {
+            let value_slice = &line[colon_pos + 1..];
+            let Some(start) = value_slice.iter().position(|&b| !b.is_ascii_whitespace()) else {
+                return Err(Error::Http(HttpClientError::MalformedHeader { offset, context: parse_context(line) }));
+            };
+            if let Ok(s) = std::str::from_utf8(&value_slice[start..])
+                && let Ok(len) = s.parse::<usize>()
+            {
+                return Ok(Some(len));
+            }
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) fn is_chunked(headers_view: &[u8]) -> bool {
+    let header_lines = headers_view.splitn(2, |&b| b == b'\n').nth(1).unwrap_or_default();
+    HttpHeaderIter::new(header_lines)
+        .filter(|h| h.key.eq_ignore_ascii_case("Transfer-Encoding"))
+        .flat_map(|h| h.value.split(','))
+        .last()
+        .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"))
+}
+
+// Where the pieces of a chunked message sit, relative to the start of the body.
+pub(crate) struct ChunkedLayout {
+    pub(crate) chunks: Vec<std::ops::Range<usize>>,
+    pub(crate) trailers: std::ops::Range<usize>,
+    pub(crate) end: usize,
+}
+
+// Ok(None) until the whole message, through the blank line after any trailers, is in `data`.
+pub(crate) fn scan_chunked(data: &[u8]) -> Result<Option<ChunkedLayout>> {
+    let parse_failure = || Error::Http(HttpClientError::HttpParseFailure);
+    let line_at = |pos: usize| {
+        data[pos..].iter().position(|&b| b == b'\n').map(|len| {
+            let line = &data[pos..pos + len];
+            (line.strip_suffix(b"\r").unwrap_or(line), pos + len + 1)
+        })
+    };
+
+    let mut chunks = Vec::new();
+    let mut pos = 0;
+    loop {
+        let Some((size_line, data_start)) = line_at(pos) else {
+            return Ok(None);
+        };
+        let size_field = size_line.split(|&b| b == b';').next().unwrap_or_default();
+        let size_str = std::str::from_utf8(size_field)?.trim();
+        let size = usize::from_str_radix(size_str, 16).map_err(|_| parse_failure())?;
+
+        if size == 0 {
+            let mut trailer_end = data_start;
+            loop {
+                let Some((line, next)) = line_at(trailer_end) else {
+                    return Ok(None);
+                };
+                if line.is_empty() {
+                    return Ok(Some(ChunkedLayout { chunks, trailers: data_start..trailer_end, end: next }));
+                }
+                trailer_end = next;
+            }
+        }
+
+        let data_end = data_start.checked_add(size).ok_or_else(parse_failure)?;
+        let Some((terminator, next)) = data.get(data_end..).and_then(|_| line_at(data_end)) else {
+            return Ok(None);
+        };
+        if !terminator.is_empty() {
+            return Err(parse_failure());
+        }
+        chunks.push(data_start..data_end);
+        pos = next;
+    }
+}
+
+#[derive(Default)]
+enum ChunkPhase {
+    #[default]
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Finished,
+}
+
+// Decodes a chunked body as it arrives, for bodies streamed to a writer rather than
+// held in memory: chunk data is passed on as soon as it is read, so only an incomplete
+// size line or trailer is ever kept back.
+#[derive(Default)]
+struct ChunkedStreamDecoder {
+    phase: ChunkPhase,
+    trailers: Vec<u8>,
+}
+
+impl ChunkedStreamDecoder {
+    fn is_finished(&self) -> bool {
+        matches!(self.phase, ChunkPhase::Finished)
+    }
+
+    // Consumes what it can from the front of `pending` and returns the number of body
+    // bytes written to `sink`. Whatever is left over waits for the next read.
+    fn feed<W: Write>(&mut self, pending: &mut Vec<u8>, sink: &mut W) -> Result<usize> {
+        let parse_failure = || Error::Http(HttpClientError::HttpParseFailure);
+        let mut pos = 0;
+        let mut written = 0;
+
+        loop {
+            let rest = &pending[pos..];
+            let line = rest.iter().position(|&b| b == b'\n').map(|len| {
+                let line = &rest[..len];
+                (line.strip_suffix(b"\r").unwrap_or(line), len + 1)
+            });
+
+            match (&self.phase, line) {
+                (ChunkPhase::Finished, _) => break,
+                (&ChunkPhase::Data(left), _) => {
+                    let n = left.min(rest.len());
+                    sink.write_all(&rest[..n]).map_err(|_| Error::Http(HttpClientError::FileWriteFailure))?;
+                    pos += n;
+                    written += n;
+                    if n < left {
+                        self.phase = ChunkPhase::Data(left - n);
+                        break;
+                    }
+                    self.phase = ChunkPhase::DataEnd;
+                }
+                (_, None) => break,
+                (ChunkPhase::Size, Some((line, consumed))) => {
+                    let size_field = line.split(|&b| b == b';').next().unwrap_or_default();
+                    let size_str = std::str::from_utf8(size_field)?.trim();
+                    let size = usize::from_str_radix(size_str, 16).map_err(|_| parse_failure())?;
+                    self.phase = if size == 0 { ChunkPhase::Trailers } else { ChunkPhase::Data(size) };
+                    pos += consumed;
+                }
+                (ChunkPhase::DataEnd, Some((line, consumed))) => {
+                    if !line.is_empty() {
+                        return Err(parse_failure());
+                    }
+                    self.phase = ChunkPhase::Size;
+                    pos += consumed;
+                }
+                (ChunkPhase::Trailers, Some((line, consumed))) => {
+                    if line.is_empty() {
+                        self.phase = ChunkPhase::Finished;
+                    } else {
+                        self.trailers.extend_from_slice(&rest[..consumed]);
+                    }
+                    pos += consumed;
+                }
+            }
+        }
+
+        pending.drain(..pos);
+        Ok(written)
+    }
+}
+
+pub(crate) fn status_forbids_body(headers_view: &[u8]) -> bool {
+    let status_line = headers_view.split(|&b| b == b'\n').next().unwrap_or_default();
+    let status_code = parse_status_line(status_line).ok().map(|status| status.code);
+
+    matches!(status_code, Some(100..=199 | 204 | 304))
+}
+
+pub(crate) fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    // Accepts the standard CRLF CRLF boundary as well as the bare LF LF used by
+    // some non-conforming servers; returns the offset of the first body byte.
+    buffer.iter().enumerate().find_map(|(pos, &b)| {
+        if b != b'\n' {
+            return None;
+        }
+        match &buffer[pos + 1..] {
+            [b'\n', ..] => Some(pos + 2),
+            [b'\r', b'\n', ..] => Some(pos + 3),
+            _ => None,
+        }
+    })
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct StatusLine<'a> {
+    pub version: &'a str,
+    pub code: u16,
+    pub reason: &'a str,
+}
+
+// Longest prefix of an offending line carried in a parse error.
+const PARSE_CONTEXT_LEN: usize = 40;
+
+fn parse_context(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(&line[..line.len().min(PARSE_CONTEXT_LEN)]).into_owned()
+}
+
+// Parses the first line of a response, with or without its line ending. The version must
+// carry the HTTP/ prefix and the code be three digits in 100-599. Errors point at the
+// field that failed, as an offset into `line`.
+pub fn parse_status_line(line: &[u8]) -> Result<StatusLine<'_>> {
+    let malformed = |offset: usize| Error::Http(HttpClientError::MalformedStatusLine { offset, context: parse_context(line) });
+    let text = std::str::from_utf8(line).map_err(|e| malformed(e.valid_up_to()))?;
+    // Every piece below is a subslice of `text`, so its offset is its distance from the start.
+    let offset_of = |part: &str| part.as_ptr() as usize - text.as_ptr() as usize;
+    let trimmed = text.trim();
+
+    let (version, rest) = trimmed
+        .split_once(|c: char| c.is_ascii_whitespace())
+        .ok_or_else(|| malformed(offset_of(trimmed) + trimmed.len()))?;
+    if !version.starts_with("HTTP/") {
+        return Err(malformed(offset_of(version)));
+    }
+    let rest = rest.trim_start();
+
+    // The reason phrase is optional; "HTTP/1.1 200" is a valid status line.
+    let (code, reason) = match rest.split_once(|c: char| c.is_ascii_whitespace()) {
+        Some((code, reason)) => (code, reason.trim_start()),
+        None => (rest, ""),
+    };
+
+    let code_value = match code.parse::<u16>() {
+        Ok(value) if code.len() == 3 && code.bytes().all(|b| b.is_ascii_digit()) && (100..=599).contains(&value) => value,
+        _ => return Err(malformed(offset_of(code))),
+    };
+
+    Ok(StatusLine { version, code: code_value, reason })
+}
+
+// Builds a borrowed view of a fully received response; shared with the async protocol.
+pub(crate) fn parse_response_view(
+    buffer: &[u8],
+    header_size: usize,
+    content_length: Option<usize>,
+    expect_body: bool,
+    max_header_count: usize,
+) -> Result<UnsafeHttpResponse<'_>> {
+    if header_size == 0 {
+        return Err(Error::Http(HttpClientError::HttpParseFailure));
+    }
+
+    let headers_block = buffer.get(..header_size).ok_or(Error::Http(HttpClientError::HttpParseFailure))?;
+
+    let mut parts = headers_block.splitn(2, |&b| b == b'\n');
+    let status_line_bytes = parts.next().unwrap_or_default();
+    let rest_of_headers_bytes = parts.next().unwrap_or_default();
+
+    let StatusLine { code: status_code, reason: status_message, .. } = parse_status_line(status_line_bytes)?;
+
+    // Obsolete line folding (RFC 7230 3.2.4) is rejected rather than unfolded: the header
+    // views borrow from the buffer, and a continuation line would otherwise parse as a
+    // header of its own.
+    let mut offset = status_line_bytes.len() + 1;
+    for line in rest_of_headers_bytes.split(|&b| b == b'\n') {
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            return Err(Error::Http(HttpClientError::MalformedHeader { offset, context: parse_context(line) }));
+        }
+        offset += line.len() + 1;
+    }
+
+    // Counted without collecting anything; the header views are parsed again only when
+    // the caller asks for them.
+    if HttpHeaderIter::new(rest_of_headers_bytes).take(max_header_count + 1).count() > max_header_count {
+        return Err(Error::Http(HttpClientError::TooManyHeaders));
+    }
+
+    // Checked so a Content-Length larger than what arrived is an error rather than a panic.
+    let body = match (expect_body, content_length) {
+        (false, _) => Some(&buffer[header_size..header_size]),
+        (true, Some(len)) => header_size.checked_add(len).and_then(|end| buffer.get(header_size..end)),
+        (true, None) => Some(&buffer[header_size..]),
+    }
+    .ok_or(Error::Http(HttpClientError::HttpParseFailure))?;
+
+    Ok(UnsafeHttpResponse {
+        status_code,
+        status_message,
+        body,
+        content_length,
+        header_block: rest_of_headers_bytes,
+        trailer_block: &[],
+        raw_head: headers_block,
+    })
+}
+
+// The response to a decoded chunked message: the head and trailers come from the raw
+// bytes in `buffer`, the body from wherever the chunk data was gathered. Trailers follow
+// the header block; `Trailer` tells callers which ones they are.
+pub(crate) fn chunked_response_view<'a>(
+    buffer: &'a [u8],
+    header_size: usize,
+    body: &'a [u8],
+    trailers: std::ops::Range<usize>,
+    max_header_count: usize,
+) -> Result<UnsafeHttpResponse<'a>> {
+    let mut res = parse_response_view(buffer, header_size, None, false, max_header_count)?;
+    res.body = body;
+    res.content_length = Some(body.len());
+    res.trailer_block = &buffer[trailers];
+    Ok(res)
+}
+
+// Parses one complete response held in memory, with no transport or connection state.
+// Malformed, truncated or hostile input always comes back as an Err, never a panic, so
+// this is the entry point to fuzz the parser through. Leading empty lines, framing and
+// chunked decoding follow the same rules as a default-configured Http1Protocol.
+pub fn parse_response(bytes: &[u8]) -> Result<SafeHttpResponse> {
+    let parse_failure = || Error::Http(HttpClientError::HttpParseFailure);
+
+    let bytes = bytes.strip_prefix(b"\r\n").or_else(|| bytes.strip_prefix(b"\n")).unwrap_or(bytes);
+    let header_size = find_header_end(bytes).ok_or_else(parse_failure)?;
+    let (headers_view, body_bytes) = bytes.split_at(header_size);
+    let expect_body = !status_forbids_body(headers_view);
+
+    if expect_body && is_chunked(headers_view) {
+        let layout = scan_chunked(body_bytes)?.ok_or_else(parse_failure)?;
+        let body: Vec<u8> = layout.chunks.iter().flat_map(|chunk| &body_bytes[chunk.clone()]).copied().collect();
+        let trailers = header_size + layout.trailers.start..header_size + layout.trailers.end;
+        return chunked_response_view(bytes, header_size, &body, trailers, DEFAULT_MAX_HEADER_COUNT).map(into_safe_response);
+    }
+
+    let content_length = content_length_of(headers_view)?;
+    parse_response_view(bytes, header_size, content_length, expect_body, DEFAULT_MAX_HEADER_COUNT).map(into_safe_response)
+}
+
+// Drops blank lines received ahead of the status line. `skipped` carries the count across
+// reads, and more than `limit` of them is a parse failure.
+pub(crate) fn skip_leading_empty_lines(buffer: &mut Vec<u8>, skipped: &mut usize, limit: usize) -> Result<()> {
+    let mut pos = 0;
+    loop {
+        match &buffer[pos..] {
+            [b'\r', b'\n', ..] => pos += 2,
+            [b'\n', ..] => pos += 1,
+            _ => break,
+        }
+        *skipped += 1;
+        if *skipped > limit {
+            return Err(Error::Http(HttpClientError::HttpParseFailure));
+        }
+    }
+    buffer.drain(..pos);
+    Ok(())
+}
+
+// HTTP/1.1 keeps the connection open unless the server says "close"; HTTP/1.0 closes it
+// unless the server opts in with "keep-alive".
+fn connection_reusable(headers_view: &[u8]) -> bool {
+    let mut lines = headers_view.splitn(2, |&b| b == b'\n');
+    let is_http10 = lines.next().unwrap_or_default().starts_with(b"HTTP/1.0");
+    let header_lines = lines.next().unwrap_or_default();
+
+    let has_token = |token: &str| {
+        HttpHeaderIter::new(header_lines)
+            .filter(|h| h.key.eq_ignore_ascii_case("Connection"))
+            .flat_map(|h| h.value.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    };
+
+    if is_http10 { has_token("keep-alive") } else { !has_token("close") }
+}
+
+fn owned_headers<'a>(headers: impl Iterator<Item = HttpHeaderView<'a>>) -> Vec<HttpOwnedHeader> {
+    headers
+        .map(|h| HttpOwnedHeader {
+            key: h.key.to_string(),
+            value: h.value.to_string(),
+        })
+        .collect()
+}
+
+pub(crate) fn into_safe_response(unsafe_res: UnsafeHttpResponse) -> SafeHttpResponse {
+    SafeHttpResponse {
+        status_code: unsafe_res.status_code,
+        status_message: unsafe_res.status_message.to_string(),
+        body: unsafe_res.body.to_vec(),
+        headers: owned_headers(unsafe_res.headers_iter()),
+        content_length: unsafe_res.content_length,
+        raw_head: unsafe_res.raw_head.to_vec(),
+        trailers: owned_headers(HttpHeaderIter::new(unsafe_res.trailer_block)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, Shutdown};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::os::unix::io::{AsRawFd, RawFd};
+    use std::thread;
+    use std::sync::mpsc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::tcp_transport::TcpTransport;
+    use crate::unix_transport::UnixTransport;
+
+    macro_rules! generate_http1_protocol_tests {
+        ($transport_type:ty, $server_logic:expr) => {
+            #[test]
+            fn connect_and_disconnect_succeeds() {
+                let server_handle = $server_logic(|_stream| {});
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                assert!(protocol.connect(&server_handle.addr, server_handle.port).is_ok());
+                assert!(protocol.disconnect().is_ok());
+            }
+
+            #[test]
+            fn perform_request_fails_if_not_connected() {
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
                     body: &[],
                     headers: vec![],
                 };
@@ -271,7 +1427,7 @@ mod tests {
                 assert!(result.is_err());
                 assert!(matches!(
                     result.unwrap_err(),
-                    Error::Transport(TransportError::SocketWriteFailure)
+                    Error::Transport(TransportError::NotConnected)
                 ));
             }
 
@@ -286,6 +1442,7 @@ mod tests {
                 });
 
                 let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_user_agent("");
                 protocol.connect(&server_handle.addr, server_handle.port).unwrap();
 
                 let request = HttpRequest {
@@ -315,6 +1472,7 @@ mod tests {
                 });
 
                 let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_user_agent("");
                 protocol.connect(&server_handle.addr, server_handle.port).unwrap();
 
                 let body = b"key=value";
@@ -342,6 +1500,192 @@ mod tests {
                 assert_eq!(captured_request, expected_request);
             }
 
+            #[test]
+            fn correctly_serializes_body_for_delete_and_put_requests() {
+                for (method, expected_request) in [
+                    (HttpMethod::Delete, &b"DELETE /items/7 HTTP/1.1\r\nHost: test-server\r\nContent-Length: 13\r\n\r\n{\"soft\":true}"[..]),
+                    (HttpMethod::Put, &b"PUT /items/7 HTTP/1.1\r\nHost: test-server\r\nContent-Length: 13\r\n\r\n{\"soft\":true}"[..]),
+                ] {
+                    let (tx, rx) = mpsc::channel();
+
+                    let server_handle = $server_logic(move |mut stream| {
+                        let mut buffer = vec![0; 1024];
+                        let bytes_read = stream.read(&mut buffer).unwrap();
+                        tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                    });
+
+                    let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                    protocol.set_user_agent("");
+                    protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method,
+                        path: "/items/7",
+                        body: b"{\"soft\":true}",
+                        headers: vec![HttpHeaderView { key: "Host", value: "test-server" }],
+                    };
+
+                    let _ = protocol.perform_request_unsafe(&request);
+
+                    let captured_request = rx.recv().unwrap();
+
+                    assert_eq!(captured_request, expected_request);
+                }
+            }
+
+            #[test]
+            fn correctly_serializes_asterisk_form_options_request() {
+                let (tx, rx) = mpsc::channel();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let bytes_read = stream.read(&mut buffer).unwrap();
+                    tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_user_agent("");
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Options,
+                    path: "*",
+                    body: &[],
+                    headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+                };
+
+                let _ = protocol.perform_request_unsafe(&request);
+
+                let captured_request = rx.recv().unwrap();
+
+                let expected_request = b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n";
+
+                assert_eq!(captured_request, expected_request);
+            }
+
+            #[test]
+            fn correctly_serializes_authority_form_connect_request() {
+                let (tx, rx) = mpsc::channel();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let bytes_read = stream.read(&mut buffer).unwrap();
+                    tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_user_agent("");
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Connect,
+                    path: RequestTarget::Authority("example.com:443").as_str(),
+                    body: &[],
+                    headers: vec![HttpHeaderView { key: "Host", value: "example.com:443" }],
+                };
+
+                let _ = protocol.perform_request_unsafe(&request);
+
+                let captured_request = rx.recv().unwrap();
+
+                let expected_request = b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n";
+
+                assert_eq!(captured_request, expected_request);
+            }
+
+            #[test]
+            fn rejects_request_target_form_not_allowed_for_method() {
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+
+                for (method, path) in [(HttpMethod::Get, "*"), (HttpMethod::Get, "example.com:443")] {
+                    let request = HttpRequest {
+                        method,
+                        path,
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let result = protocol.perform_request_unsafe(&request);
+
+                    assert!(result.is_err());
+                    assert!(matches!(
+                        result.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    ));
+                }
+            }
+
+            #[test]
+            fn pipelines_requests_and_returns_responses_in_order() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut received = Vec::new();
+                    let mut buffer = [0u8; 1024];
+                    while received.windows(4).filter(|w| w == b"\r\n\r\n").count() < 3 {
+                        let n = stream.read(&mut buffer).unwrap();
+                        if n == 0 { return; }
+                        received.extend_from_slice(&buffer[..n]);
+                    }
+
+                    // All three responses land in a single write, so one read can hold several.
+                    stream.write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\none\
+                          HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\ntwo\
+                          HTTP/1.1 404 Not Found\r\nContent-Length: 5\r\n\r\nthree"
+                    ).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let requests: Vec<HttpRequest> = ["/one", "/two", "/three"]
+                    .iter()
+                    .map(|path| HttpRequest {
+                        method: HttpMethod::Get,
+                        path,
+                        body: &[],
+                        headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+                    })
+                    .collect();
+
+                let responses = protocol.pipeline(&requests).unwrap();
+
+                assert_eq!(responses.len(), 3);
+                assert_eq!(responses[0].status_code, 200);
+                assert_eq!(responses[0].body, b"one");
+                assert_eq!(responses[1].status_code, 200);
+                assert_eq!(responses[1].body, b"two");
+                assert_eq!(responses[2].status_code, 404);
+                assert_eq!(responses[2].status_message, "Not Found");
+                assert_eq!(responses[2].body, b"three");
+            }
+
+            #[test]
+            fn rejects_line_breaks_in_path_and_headers() {
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+
+                let injected_path = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/index\r\nX-Injected: 1",
+                    body: &[],
+                    headers: vec![],
+                };
+                assert_eq!(
+                    protocol.perform_request_unsafe(&injected_path).unwrap_err(),
+                    Error::Http(HttpClientError::InvalidRequest)
+                );
+
+                let injected_header = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![HttpHeaderView { key: "X-Name", value: "value\nX-Injected: 1" }],
+                };
+                assert_eq!(
+                    protocol.perform_request_unsafe(&injected_header).unwrap_err(),
+                    Error::Http(HttpClientError::InvalidRequest)
+                );
+            }
+
             #[test]
             fn successfully_parses_response_with_content_length() {
                 let canned_response = b"HTTP/1.1 200 OK\r\n\
@@ -352,7 +1696,7 @@ mod tests {
 
                 let server_handle = $server_logic(|mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
                     stream.write_all(canned_response).unwrap();
                     stream.shutdown(Shutdown::Write).unwrap();
                 });
@@ -374,14 +1718,85 @@ mod tests {
 
                 assert_eq!(res.status_code, 200);
                 assert_eq!(res.status_message, "OK");
-                assert_eq!(res.headers.len(), 2);
-                assert_eq!(res.headers[0].key, "Content-Type");
-                assert_eq!(res.headers[0].value, "text/plain");
-                assert_eq!(res.headers[1].key, "Content-Length");
-                assert_eq!(res.headers[1].value, "12");
+                assert_eq!(res.headers().len(), 2);
+                assert_eq!(res.headers()[0].key, "Content-Type");
+                assert_eq!(res.headers()[0].value, "text/plain");
+                assert_eq!(res.headers()[1].key, "Content-Length");
+                assert_eq!(res.headers()[1].value, "12");
                 assert_eq!(res.body, b"Hello Client");
             }
 
+            #[test]
+            fn parses_status_line_with_missing_or_padded_reason_phrase() {
+                let cases: [(&'static [u8], &str); 3] = [
+                    (b"HTTP/1.1 200\r\nContent-Length: 0\r\n\r\n", ""),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", "OK"),
+                    (b"HTTP/1.1  200  OK\r\nContent-Length: 0\r\n\r\n", "OK"),
+                ];
+
+                for (canned_response, expected_message) in cases {
+                    let server_handle = $server_logic(move |mut stream| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(canned_response).unwrap();
+                        stream.shutdown(Shutdown::Write).unwrap();
+                    });
+
+                    let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                    protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let res = protocol.perform_request_unsafe(&request).unwrap();
+
+                    assert_eq!(res.status_code, 200);
+                    assert_eq!(res.status_message, expected_message);
+                    assert!(res.body.is_empty());
+                }
+            }
+
+            #[test]
+            fn successfully_parses_lf_only_response() {
+                let canned_response = b"HTTP/1.1 200 OK\n\
+                                       Content-Type: text/plain\n\
+                                       Content-Length: 7\n\
+                                       \n\
+                                       LF only";
+
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(canned_response).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+
+                assert_eq!(res.status_code, 200);
+                assert_eq!(res.status_message, "OK");
+                assert_eq!(res.headers().len(), 2);
+                assert_eq!(res.headers()[0].key, "Content-Type");
+                assert_eq!(res.headers()[0].value, "text/plain");
+                assert_eq!(res.headers()[1].key, "Content-Length");
+                assert_eq!(res.headers()[1].value, "7");
+                assert_eq!(res.content_length, Some(7));
+                assert_eq!(res.body, b"LF only");
+            }
+
             #[test]
             fn successfully_reads_body_on_connection_close() {
                 let canned_response = b"HTTP/1.1 200 OK\r\n\
@@ -391,7 +1806,7 @@ mod tests {
 
                 let server_handle = $server_logic(|mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
                     stream.write_all(canned_response).unwrap();
                 });
 
@@ -431,7 +1846,7 @@ mod tests {
 
                 let server_handle = $server_logic(move |mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
                     stream.write_all(canned_response.as_bytes()).unwrap();
                     stream.write_all(response_body).unwrap();
                     stream.shutdown(Shutdown::Write).unwrap();
@@ -455,29 +1870,265 @@ mod tests {
                 assert_eq!(res.status_code, 404);
                 assert_eq!(res.status_message, "Not Found");
 
-                assert_eq!(res.headers.len(), 4);
-                assert_eq!(res.headers[0].key, "Connection");
-                assert_eq!(res.headers[0].value, "close");
-                assert_eq!(res.headers[1].key, "Content-Type");
-                assert_eq!(res.headers[1].value, "application/json");
-                assert_eq!(res.headers[2].key, "X-Request-ID");
-                assert_eq!(res.headers[2].value, "abc-123");
-                assert_eq!(res.headers[3].key, "Content-Length");
-                assert_eq!(res.headers[3].value, "21");
+                assert_eq!(res.headers().len(), 4);
+                assert_eq!(res.headers()[0].key, "Connection");
+                assert_eq!(res.headers()[0].value, "close");
+                assert_eq!(res.headers()[1].key, "Content-Type");
+                assert_eq!(res.headers()[1].value, "application/json");
+                assert_eq!(res.headers()[2].key, "X-Request-ID");
+                assert_eq!(res.headers()[2].value, "abc-123");
+                assert_eq!(res.headers()[3].key, "Content-Length");
+                assert_eq!(res.headers()[3].value, "21");
 
                 assert_eq!(res.body, response_body);
             }
 
             #[test]
-            fn handles_zero_content_length_response() {
-                let canned_response = b"HTTP/1.1 204 No Content\r\n\
+            fn lazy_header_iteration_matches_eager_parse() {
+                let canned_response = b"HTTP/1.1 200 OK\r\n\
+                                       Content-Type: text/plain\r\n\
+                                       X-Empty:\r\n\
+                                       X-Spaced:   padded value  \r\n\
+                                       Content-Length: 2\r\n\
+                                       \r\n\
+                                       ok";
+
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(canned_response).unwrap();
+                    stream.shutdown(Shutdown::Write).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+
+                let lazy_headers: Vec<HttpHeaderView> = res.headers_iter().collect();
+                let eager_headers = parse_response(canned_response).unwrap().headers;
+                assert_eq!(lazy_headers.len(), 4);
+                assert_eq!(lazy_headers.len(), eager_headers.len());
+                for (lazy, eager) in lazy_headers.iter().zip(&eager_headers) {
+                    assert_eq!((lazy.key, lazy.value), (eager.key.as_str(), eager.value.as_str()));
+                }
+                assert_eq!(res.header("x-spaced"), Some("padded value  "));
+                assert_eq!(res.header("content-type"), Some("text/plain"));
+                assert_eq!(res.header("X-Missing"), None);
+            }
+
+            #[test]
+            fn not_modified_with_bogus_content_length_returns_promptly() {
+                let (tx, rx) = mpsc::channel::<()>();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 50\r\n\r\n")
+                        .unwrap();
+                    // Keep the connection open so only the status code can end the read.
+                    let _ = rx.recv();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/cached",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.status_code, 304);
+                assert!(res.body.is_empty());
+                assert_eq!(res.content_length, Some(50));
+
+                tx.send(()).unwrap();
+            }
+
+            #[test]
+            fn head_response_with_content_length_returns_promptly() {
+                let (tx, rx) = mpsc::channel::<()>();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1234\r\n\r\n")
+                        .unwrap();
+                    let _ = rx.recv();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Head,
+                    path: "/large",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.status_code, 200);
+                assert!(res.body.is_empty());
+                assert_eq!(res.content_length, Some(1234));
+
+                tx.send(()).unwrap();
+            }
+
+            #[test]
+            fn no_content_with_bogus_content_length_returns_promptly() {
+                let (tx, rx) = mpsc::channel::<()>();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream
+                        .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 20\r\n\r\n")
+                        .unwrap();
+                    let _ = rx.recv();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Delete,
+                    path: "/item/7",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.status_code, 204);
+                assert!(res.body.is_empty());
+                assert_eq!(res.content_length, Some(20));
+
+                tx.send(()).unwrap();
+            }
+
+            #[test]
+            fn uploads_reader_as_chunked_body() {
+                let (tx, rx) = mpsc::channel();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut received = Vec::new();
+                    let mut buffer = [0u8; 1024];
+                    while !received.ends_with(b"0\r\n\r\n") {
+                        let n = stream.read(&mut buffer).unwrap();
+                        if n == 0 { break; }
+                        received.extend_from_slice(&buffer[..n]);
+                    }
+                    tx.send(received).unwrap();
+                    stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_user_agent("");
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Post,
+                    path: "/upload",
+                    body: &[],
+                    headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+                };
+
+                // A chained reader yields each part from a separate read, so one chunk apiece.
+                let body = std::io::Cursor::new(&b"first"[..])
+                    .chain(std::io::Cursor::new(&b"-second-"[..]))
+                    .chain(std::io::Cursor::new(&b"third chunk of data"[..]));
+
+                let res = protocol.perform_request_chunked(&request, body).unwrap();
+                assert_eq!(res.status_code, 201);
+
+                let captured = rx.recv().unwrap();
+                assert_eq!(
+                    captured,
+                    b"POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n\
+                      5\r\nfirst\r\n\
+                      8\r\n-second-\r\n\
+                      13\r\nthird chunk of data\r\n\
+                      0\r\n\r\n"
+                );
+            }
+
+            #[test]
+            fn chunked_upload_rejects_content_length() {
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+
+                let request = HttpRequest {
+                    method: HttpMethod::Post,
+                    path: "/upload",
+                    body: &[],
+                    headers: vec![HttpHeaderView { key: "Content-Length", value: "10" }],
+                };
+
+                let result = protocol.perform_request_chunked(&request, std::io::Cursor::new(&b"0123456789"[..]));
+                assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+            }
+
+            #[test]
+            fn streams_large_body_in_bounded_flushes() {
+                let body: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+                let expected_body = body.clone();
+                let (tx, rx) = mpsc::channel();
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut received = Vec::new();
+                    let mut buffer = vec![0u8; 64 * 1024];
+                    let header_end = loop {
+                        let n = stream.read(&mut buffer).unwrap();
+                        received.extend_from_slice(&buffer[..n]);
+                        if let Some(pos) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                            break pos + 4;
+                        }
+                    };
+                    while received.len() < header_end + expected_body.len() {
+                        let n = stream.read(&mut buffer).unwrap();
+                        received.extend_from_slice(&buffer[..n]);
+                    }
+                    tx.send(received[header_end..].to_vec()).unwrap();
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_body_flush_size(Some(4096));
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Post,
+                    path: "/large",
+                    body: &body,
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.status_code, 200);
+                assert!(rx.recv().unwrap() == body);
+            }
+
+            #[test]
+            fn handles_zero_content_length_response() {
+                let canned_response = b"HTTP/1.1 204 No Content\r\n\
                                        Connection: close\r\n\
                                        Content-Length: 0\r\n\
                                        \r\n";
 
                 let server_handle = $server_logic(|mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
                     stream.write_all(canned_response).unwrap();
                     stream.shutdown(Shutdown::Write).unwrap();
                 });
@@ -498,9 +2149,9 @@ mod tests {
                 let res = result.unwrap();
 
                 assert_eq!(res.status_code, 204);
-                assert_eq!(res.headers.len(), 2);
-                assert_eq!(res.headers[1].key, "Content-Length");
-                assert_eq!(res.headers[1].value, "0");
+                assert_eq!(res.headers().len(), 2);
+                assert_eq!(res.headers()[1].key, "Content-Length");
+                assert_eq!(res.headers()[1].value, "0");
                 assert!(res.body.is_empty());
             }
 
@@ -515,7 +2166,7 @@ mod tests {
 
                 let server_handle = $server_logic(move |mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
 
                     stream.write_all(canned_response_headers.as_bytes()).unwrap();
                     stream.write_all(&body_for_server).unwrap();
@@ -542,6 +2193,51 @@ mod tests {
                 assert_eq!(res.body, large_body.as_slice());
             }
 
+            #[test]
+            fn reads_body_with_content_length_and_immediate_reset() {
+                let canned_response = b"HTTP/1.1 200 OK\r\n\
+                                       Connection: close\r\n\
+                                       Content-Length: 13\r\n\
+                                       \r\n\
+                                       Closing body.";
+
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(canned_response).unwrap();
+
+                    let linger = libc::linger { l_onoff: 1, l_linger: 0 };
+                    unsafe {
+                        libc::setsockopt(
+                            stream.as_raw_fd(),
+                            libc::SOL_SOCKET,
+                            libc::SO_LINGER,
+                            &linger as *const _ as *const libc::c_void,
+                            std::mem::size_of::<libc::linger>() as u32,
+                        );
+                    }
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let result = protocol.perform_request_unsafe(&request);
+
+                assert!(result.is_ok());
+                let res = result.unwrap();
+
+                assert_eq!(res.status_code, 200);
+                assert_eq!(res.content_length, Some(13));
+                assert_eq!(res.body, b"Closing body.");
+            }
+
             #[test]
             fn fails_gracefully_on_bad_content_length() {
                 let response_body = b"short body";
@@ -551,7 +2247,7 @@ mod tests {
 
                 let server_handle = $server_logic(move |mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let _ = stream.read(&mut buffer).unwrap();
                     stream.write_all(canned_response_headers.as_bytes()).unwrap();
                     stream.write_all(response_body).unwrap();
                     stream.shutdown(Shutdown::Write).unwrap();
@@ -577,15 +2273,17 @@ mod tests {
             }
 
             #[test]
-            fn safe_request_returns_owning_deep_copy() {
+            fn send_raw_writes_bytes_verbatim_and_parses_response() {
                 let canned_response = b"HTTP/1.1 200 OK\r\n\
-                                       Content-Length: 11\r\n\
+                                       Content-Length: 3\r\n\
                                        \r\n\
-                                       Safe Buffer";
+                                       raw";
+                let (tx, rx) = mpsc::channel();
 
-                let server_handle = $server_logic(|mut stream| {
+                let server_handle = $server_logic(move |mut stream| {
                     let mut buffer = vec![0; 1024];
-                    stream.read(&mut buffer).unwrap();
+                    let bytes_read = stream.read(&mut buffer).unwrap();
+                    tx.send(buffer[..bytes_read].to_vec()).unwrap();
                     stream.write_all(canned_response).unwrap();
                     stream.shutdown(Shutdown::Write).unwrap();
                 });
@@ -593,6 +2291,39 @@ mod tests {
                 let mut protocol = Http1Protocol::new(<$transport_type>::new());
                 protocol.connect(&server_handle.addr, server_handle.port).unwrap();
 
+                let raw_request = b"GET / HTTP/1.1\r\n\r\n";
+                let result = protocol.send_raw(raw_request);
+
+                assert!(result.is_ok());
+                let res = result.unwrap();
+
+                assert_eq!(res.status_code, 200);
+                assert_eq!(res.status_message, "OK");
+                assert_eq!(res.body, b"raw");
+                assert_eq!(res.content_length, Some(3));
+
+                let captured_request = rx.recv().unwrap();
+                assert_eq!(captured_request, raw_request);
+            }
+
+            #[test]
+            fn body_read_deadline_fires_on_slow_drip() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n").unwrap();
+                    for _ in 0..100 {
+                        if stream.write_all(b"x").is_err() {
+                            break;
+                        }
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_body_read_deadline(Some(Duration::from_millis(200)));
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
                 let request = HttpRequest {
                     method: HttpMethod::Get,
                     path: "/",
@@ -600,26 +2331,1575 @@ mod tests {
                     headers: vec![],
                 };
 
-                let result = protocol.perform_request_safe(&request);
+                let started_at = Instant::now();
+                let result = protocol.perform_request_unsafe(&request);
 
-                assert!(result.is_ok());
-                let res = result.unwrap();
+                assert!(result.is_err());
+                assert!(matches!(
+                    result.unwrap_err(),
+                    Error::Transport(TransportError::TimedOut)
+                ));
+                assert!(started_at.elapsed() < Duration::from_millis(1500));
+            }
+
+            #[test]
+            fn fails_on_empty_or_blank_content_length_value() {
+                let cases: [(&'static [u8], bool); 3] = [
+                    (b"HTTP/1.1 200 OK\r\nContent-Length:\r\n\r\nbody", false),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length:    \r\n\r\nbody", false),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody", true),
+                ];
+
+                for (canned_response, should_succeed) in cases {
+                    let server_handle = $server_logic(move |mut stream| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(canned_response).unwrap();
+                        stream.shutdown(Shutdown::Write).unwrap();
+                    });
+
+                    let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                    protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let result = protocol.perform_request_unsafe(&request);
+
+                    if should_succeed {
+                        let res = result.unwrap();
+                        assert_eq!(res.content_length, Some(4));
+                        assert_eq!(res.body, b"body");
+                    } else {
+                        assert!(matches!(
+                            result.unwrap_err(),
+                            Error::Http(HttpClientError::MalformedHeader { offset: 17, .. })
+                        ));
+                    }
+                }
+            }
+
+            #[test]
+            fn strict_framing_rejects_short_and_overlong_bodies() {
+                let cases: [(&'static [u8], bool, bool); 4] = [
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort", true, false),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbodyEXTRA", true, false),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbodyEXTRA", false, true),
+                    (b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody", true, true),
+                ];
+
+                for (canned_response, strict, should_succeed) in cases {
+                    let server_handle = $server_logic(move |mut stream| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(canned_response).unwrap();
+                        let _ = stream.shutdown(Shutdown::Write);
+                    });
+
+                    let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                    protocol.set_strict_framing(strict);
+                    protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let result = protocol.perform_request_unsafe(&request);
+
+                    if should_succeed {
+                        assert_eq!(result.unwrap().body, b"body");
+                    } else {
+                        assert!(matches!(
+                            result.unwrap_err(),
+                            Error::Http(HttpClientError::HttpParseFailure)
+                        ));
+                    }
+                }
+            }
+
+            #[test]
+            fn single_leading_empty_line_is_skipped() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(b"\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                    let _ = stream.shutdown(Shutdown::Write);
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
 
+                let res = protocol.perform_request_unsafe(&request).unwrap();
                 assert_eq!(res.status_code, 200);
-                assert_eq!(res.body, b"Safe Buffer");
+                assert_eq!(res.body, b"ok");
+            }
 
-                assert_ne!(
-                    res.body.as_ptr(),
-                    protocol.get_internal_buffer_ptr_for_test()
-                );
+            #[test]
+            fn extra_leading_empty_lines_depend_on_lenient_parsing() {
+                for lenient in [false, true] {
+                    let server_handle = $server_logic(move |mut stream| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(b"\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                        let _ = stream.shutdown(Shutdown::Write);
+                    });
+
+                    let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                    protocol.set_lenient_parsing(lenient);
+                    protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let result = protocol.perform_request_unsafe(&request);
+
+                    if lenient {
+                        let res = result.unwrap();
+                        assert_eq!(res.status_code, 200);
+                        assert_eq!(res.body, b"ok");
+                    } else {
+                        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::HttpParseFailure));
+                    }
+                }
             }
-        };
-    }
 
-    struct ServerHandle {
-        _thread: thread::JoinHandle<()>,
-        addr: String,
-        port: u16,
+            #[test]
+            fn lenient_parsing_bounds_leading_empty_lines() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(b"\n\n\n\n\n\nHTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+                    let _ = stream.shutdown(Shutdown::Write);
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.set_lenient_parsing(true);
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let result = protocol.perform_request_unsafe(&request);
+                assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::HttpParseFailure));
+            }
+
+            #[test]
+            fn last_raw_response_exposes_unparseable_bytes() {
+                let garbage: &'static [u8] = b"NOT HTTP\x00\xff AT ALL";
+
+                let server_handle = $server_logic(move |mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(garbage).unwrap();
+                    stream.shutdown(Shutdown::Write).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let result = protocol.perform_request_unsafe(&request);
+
+                assert!(matches!(
+                    result.unwrap_err(),
+                    Error::Http(HttpClientError::HttpParseFailure)
+                ));
+                assert_eq!(protocol.last_raw_response(), garbage);
+                assert_eq!(
+                    protocol.last_raw_response_preview(10),
+                    "4e 4f 54 20 48 54 54 50 00 ff |NOT HTTP..| (+7 bytes)"
+                );
+            }
+
+            #[test]
+            fn reset_clears_request_state_but_keeps_capacity() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 4096];
+                    for _ in 0..2 {
+                        let _ = stream.read(&mut buffer).unwrap();
+                        let body = vec![b'r'; 2000];
+                        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2000\r\n\r\n").unwrap();
+                        stream.write_all(&body).unwrap();
+                    }
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.body.len(), 2000);
+
+                let capacity_before = protocol.buffer.capacity();
+                protocol.reset();
+
+                assert!(protocol.buffer.is_empty());
+                assert_eq!(protocol.buffer.capacity(), capacity_before);
+                assert_eq!(protocol.header_size, 0);
+                assert_eq!(protocol.get_content_length_for_test(), None);
+
+                let res = protocol.perform_request_unsafe(&request).unwrap();
+                assert_eq!(res.status_code, 200);
+                assert_eq!(res.body, vec![b'r'; 2000].as_slice());
+            }
+
+            #[test]
+            fn reports_fresh_then_reused_connection() {
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    for _ in 0..2 {
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                    }
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+                assert!(!protocol.last_connection_was_reused());
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                protocol.perform_request_unsafe(&request).unwrap();
+                assert!(!protocol.last_connection_was_reused());
+
+                protocol.perform_request_unsafe(&request).unwrap();
+                assert!(protocol.last_connection_was_reused());
+
+                protocol.disconnect().unwrap();
+                assert!(!protocol.last_connection_was_reused());
+            }
+
+            #[test]
+            fn safe_request_returns_owning_deep_copy() {
+                let canned_response = b"HTTP/1.1 200 OK\r\n\
+                                       Content-Length: 11\r\n\
+                                       \r\n\
+                                       Safe Buffer";
+
+                let server_handle = $server_logic(|mut stream| {
+                    let mut buffer = vec![0; 1024];
+                    let _ = stream.read(&mut buffer).unwrap();
+                    stream.write_all(canned_response).unwrap();
+                    stream.shutdown(Shutdown::Write).unwrap();
+                });
+
+                let mut protocol = Http1Protocol::new(<$transport_type>::new());
+                protocol.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                let request = HttpRequest {
+                    method: HttpMethod::Get,
+                    path: "/",
+                    body: &[],
+                    headers: vec![],
+                };
+
+                let result = protocol.perform_request_safe(&request);
+
+                assert!(result.is_ok());
+                let res = result.unwrap();
+
+                assert_eq!(res.status_code, 200);
+                assert_eq!(res.body, b"Safe Buffer");
+
+                assert_ne!(
+                    res.body.as_ptr(),
+                    protocol.get_internal_buffer_ptr_for_test()
+                );
+            }
+        };
+    }
+
+    static TEST_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    struct ServerHandle {
+        _thread: thread::JoinHandle<()>,
+        addr: String,
+        port: u16,
+    }
+
+    #[derive(Default)]
+    struct CountingTransport {
+        writes: Vec<Vec<u8>>,
+        response: Vec<u8>,
+        read_pos: usize,
+    }
+
+    impl Transport for CountingTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.writes.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let remaining = &self.response[self.read_pos..];
+            if remaining.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            let n = remaining.len().min(buf.len());
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.read_pos += n;
+            Ok(n)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // Serves `response` at most `chunk` bytes per read, recording where each read landed
+    // relative to the start of the protocol's buffer.
+    struct ChunkedReadTransport {
+        response: Vec<u8>,
+        read_pos: usize,
+        chunk: usize,
+        buffer_starts: Vec<usize>,
+    }
+
+    impl Transport for ChunkedReadTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            // Every read so far has been appended, so the buffer starts read_pos bytes back.
+            self.buffer_starts.push(buf.as_ptr() as usize - self.read_pos);
+            let remaining = &self.response[self.read_pos..];
+            if remaining.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            let n = remaining.len().min(buf.len()).min(self.chunk);
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.read_pos += n;
+            Ok(n)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // Answers each request with the next canned response, never reading past its end.
+    struct ScriptedTransport {
+        responses: std::collections::VecDeque<Vec<u8>>,
+        current: Vec<u8>,
+    }
+
+    impl Transport for ScriptedTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            if self.current.is_empty() {
+                self.current = self.responses.pop_front().ok_or(Error::Transport(TransportError::ConnectionClosed))?;
+            }
+            let n = self.current.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.current[..n]);
+            self.current.drain(..n);
+            Ok(n)
+        }
+
+        fn input_pending(&self) -> bool {
+            !self.current.is_empty() || !self.responses.is_empty()
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // Accepts the first `accepted` bytes, then reports zero-byte writes forever.
+    struct StalledTransport {
+        accepted: usize,
+    }
+
+    impl Transport for StalledTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = buf.len().min(self.accepted);
+            self.accepted -= n;
+            Ok(n)
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Ok(0)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn zero_byte_write_surfaces_connection_closed() {
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+        for accepted in [0, 5] {
+            let mut protocol = Http1Protocol::new(StalledTransport { accepted });
+            assert_eq!(
+                protocol.perform_request_unsafe(&request).unwrap_err(),
+                Error::Transport(TransportError::ConnectionClosed)
+            );
+        }
+    }
+
+    fn small_post_with_buffering(enabled: bool) -> Vec<Vec<u8>> {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        protocol.set_user_agent("");
+        protocol.set_write_buffering(enabled);
+
+        let request = HttpRequest {
+            method: HttpMethod::Post,
+            path: "/submit",
+            body: b"payload",
+            headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+        };
+        let res = protocol.perform_request_unsafe(&request).unwrap();
+        assert_eq!(res.body, b"ok");
+
+        protocol.transport.writes
+    }
+
+    fn sent_request_head(configure: impl FnOnce(&mut Http1Protocol<CountingTransport>), headers: Vec<HttpHeaderView>) -> String {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 204 No Content\r\n\r\n".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        configure(&mut protocol);
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers };
+        protocol.perform_request_unsafe(&request).unwrap();
+
+        String::from_utf8(protocol.transport.writes.concat()).unwrap()
+    }
+
+    #[test]
+    fn default_user_agent_carries_crate_version() {
+        let head = sent_request_head(|_| {}, vec![]);
+        let expected = format!("User-Agent: httprust/{}\r\n", env!("CARGO_PKG_VERSION"));
+        assert!(head.contains(&expected));
+    }
+
+    #[test]
+    fn user_agent_can_be_overridden_or_suppressed() {
+        let head = sent_request_head(|p| p.set_user_agent("probe/2.0"), vec![]);
+        assert!(head.contains("User-Agent: probe/2.0\r\n"));
+
+        let head = sent_request_head(|p| p.set_user_agent(""), vec![]);
+        assert!(!head.contains("User-Agent"));
+    }
+
+    #[test]
+    fn caller_user_agent_header_replaces_default() {
+        let head = sent_request_head(|_| {}, vec![HttpHeaderView { key: "user-agent", value: "mine" }]);
+        assert_eq!(head.matches("ser-agent").count(), 1);
+        assert!(head.contains("user-agent: mine\r\n"));
+    }
+
+    #[test]
+    fn duplicate_request_headers_are_sent_in_order() {
+        let head = sent_request_head(
+            |p| p.set_user_agent(""),
+            vec![
+                HttpHeaderView { key: "X-Custom", value: "first" },
+                HttpHeaderView { key: "Host", value: "example.com" },
+                HttpHeaderView { key: "X-Custom", value: "second" },
+            ],
+        );
+        assert_eq!(head, "GET / HTTP/1.1\r\nX-Custom: first\r\nHost: example.com\r\nX-Custom: second\r\n\r\n");
+    }
+
+    #[test]
+    fn too_many_response_headers_are_rejected() {
+        let mut response = b"HTTP/1.1 200 OK\r\n".to_vec();
+        for i in 0..1000 {
+            response.extend_from_slice(format!("X-Filler-{}: {}\r\n", i, i).as_bytes());
+        }
+        response.extend_from_slice(b"Content-Length: 0\r\n\r\n");
+
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        protocol.set_max_header_count(100);
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(
+            protocol.perform_request_safe(&request).unwrap_err(),
+            Error::Http(HttpClientError::TooManyHeaders)
+        );
+    }
+
+    #[test]
+    fn response_at_header_count_limit_is_accepted() {
+        let mut response = b"HTTP/1.1 200 OK\r\n".to_vec();
+        for i in 0..9 {
+            response.extend_from_slice(format!("X-Filler-{}: {}\r\n", i, i).as_bytes());
+        }
+        response.extend_from_slice(b"Content-Length: 2\r\n\r\nok");
+
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        protocol.set_max_header_count(10);
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_safe(&request).unwrap();
+        assert_eq!(res.headers.len(), 10);
+        assert_eq!(res.body, b"ok");
+    }
+
+    #[test]
+    fn folded_response_header_is_rejected() {
+        let response = b"HTTP/1.1 200 OK\r\n\
+                         X-Long: first part\r\n\
+                         \tsecond part\r\n\
+                         Content-Length: 0\r\n\r\n"
+            .to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(
+            protocol.perform_request_safe(&request).unwrap_err(),
+            Error::Http(HttpClientError::MalformedHeader { offset: 37, context: "\tsecond part".to_string() })
+        );
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_line() {
+        let head = b"HTTP/1.1 200 OK\r\nServer: test\r\nContent-Length:  \r\n\r\n";
+        let bad_line_at = head.windows(15).position(|w| w == b"Content-Length:").unwrap();
+        let Err(Error::Http(HttpClientError::MalformedHeader { offset, context })) = parse_response(head) else {
+            panic!("expected a header parse error");
+        };
+        assert_eq!(offset, bad_line_at);
+        assert_eq!(context, "Content-Length:  ");
+
+        let long_reason = format!("HTTP/1.1 20x {}\r\n\r\n", "r".repeat(100));
+        let Err(Error::Http(HttpClientError::MalformedStatusLine { offset, context })) = parse_response(long_reason.as_bytes())
+        else {
+            panic!("expected a status line parse error");
+        };
+        assert_eq!(offset, 9);
+        assert_eq!(context.len(), PARSE_CONTEXT_LEN);
+        assert!(context.starts_with("HTTP/1.1 20x rrr"));
+    }
+
+    #[test]
+    fn length_framed_body_is_read_into_a_single_allocation() {
+        let body = vec![b'z'; 1 << 20];
+        let mut response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        response.extend_from_slice(&body);
+        let transport = ChunkedReadTransport { response, read_pos: 0, chunk: 64 * 1024, buffer_starts: Vec::new() };
+        let mut protocol = Http1Protocol::new(transport);
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_unsafe(&request).unwrap();
+        assert_eq!(res.body.len(), body.len());
+
+        // The first read finds the headers; every body read after it lands in one buffer.
+        let starts = &protocol.transport.buffer_starts;
+        assert!(starts.len() > 2);
+        assert!(starts[1..].iter().all(|&start| start == starts[1]));
+        assert_eq!(protocol.buffer.capacity(), protocol.buffer.len());
+    }
+
+    #[test]
+    fn auto_shrink_releases_capacity_after_large_response() {
+        let mut large = b"HTTP/1.1 200 OK\r\nContent-Length: 1000000\r\n\r\n".to_vec();
+        large.resize(large.len() + 1_000_000, b'L');
+        let small = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nsmall".to_vec();
+        let script = |auto_shrink: bool| {
+            let responses = [large.clone(), small.clone(), small.clone(), small.clone()].into();
+            let mut protocol = Http1Protocol::new(ScriptedTransport { responses, current: Vec::new() });
+            protocol.set_auto_shrink(auto_shrink);
+            protocol
+        };
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+        let mut shrinking = script(true);
+        shrinking.perform_request_safe(&request).unwrap();
+        assert!(shrinking.buffer.capacity() >= 1_000_000);
+        // The request after the large response still measures against it, so the policy
+        // only kicks in on the one after that.
+        assert_eq!(shrinking.perform_request_safe(&request).unwrap().body, b"small");
+        assert!(shrinking.buffer.capacity() >= 1_000_000);
+        for _ in 0..2 {
+            assert_eq!(shrinking.perform_request_safe(&request).unwrap().body, b"small");
+            assert!(shrinking.buffer.capacity() < 16 * 1024);
+        }
+
+        let mut keeping = script(false);
+        for _ in 0..4 {
+            keeping.perform_request_safe(&request).unwrap();
+        }
+        assert!(keeping.buffer.capacity() >= 1_000_000);
+
+        keeping.shrink_buffer_to(0);
+        assert!(keeping.buffer.capacity() < 1024);
+    }
+
+    #[test]
+    fn strict_framing_rejects_surplus_bytes_sent_after_the_body() {
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        for strict in [true, false] {
+            // The surplus arrives as a second segment, after the read that completed the body.
+            let responses = [b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody".to_vec(), b"EXTRA".to_vec()].into();
+            let mut protocol = Http1Protocol::new(ScriptedTransport { responses, current: Vec::new() });
+            protocol.set_strict_framing(strict);
+
+            let result = protocol.perform_request_unsafe(&request);
+
+            if strict {
+                assert!(matches!(result.unwrap_err(), Error::Http(HttpClientError::HttpParseFailure)));
+            } else {
+                assert_eq!(result.unwrap().body, b"body");
+            }
+        }
+    }
+
+    #[test]
+    fn adaptive_buffering_stops_reallocating_after_warm_up() {
+        let mut regular = b"HTTP/1.1 200 OK\r\nContent-Length: 20000\r\n\r\n".to_vec();
+        regular.resize(regular.len() + 20_000, b'r');
+        let mut large = b"HTTP/1.1 200 OK\r\nContent-Length: 1000000\r\n\r\n".to_vec();
+        large.resize(large.len() + 1_000_000, b'L');
+
+        let mut responses: std::collections::VecDeque<Vec<u8>> = std::iter::repeat_n(regular.clone(), 50).collect();
+        responses.insert(10, large);
+        let mut protocol = Http1Protocol::new(ScriptedTransport { responses, current: Vec::new() });
+        protocol.set_adaptive_buffering(true);
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+        // A reallocation shows up as the buffer moving or changing capacity between requests.
+        let mut reallocations = Vec::new();
+        for _ in 0..51 {
+            let before = (protocol.buffer.as_ptr(), protocol.buffer.capacity());
+            protocol.perform_request_unsafe(&request).unwrap();
+            reallocations.push(before != (protocol.buffer.as_ptr(), protocol.buffer.capacity()));
+        }
+
+        // Warm-up costs a reallocation, as does the large response at index 10. Its capacity
+        // is then given back in a few steps as the average decays, after which the size
+        // holds steady.
+        assert!(reallocations[0]);
+        assert!(!reallocations[1..10].iter().any(|&realloc| realloc));
+        assert!(reallocations[10]);
+        assert!(reallocations[11..].iter().filter(|&&realloc| realloc).count() <= 4);
+        assert!(!reallocations[35..].iter().any(|&realloc| realloc));
+        assert!(protocol.buffer.capacity() < 2 * regular.len());
+    }
+
+    #[test]
+    fn timings_cover_each_phase_of_a_request() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nfirst").unwrap();
+            thread::sleep(Duration::from_millis(50));
+            stream.write_all(b"-last").unwrap();
+        });
+
+        let mut protocol = Http1Protocol::new(TcpTransport::new());
+        assert_eq!(protocol.last_timings(), RequestTimings::default());
+        protocol.set_record_timings(true);
+        protocol.connect("127.0.0.1", port).unwrap();
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let started = Instant::now();
+        assert_eq!(protocol.perform_request_safe(&request).unwrap().body, b"first-last");
+        let elapsed = started.elapsed();
+
+        let timings = protocol.last_timings();
+        assert!(timings.time_to_first_byte >= Duration::from_millis(40));
+        assert!(timings.body_read >= Duration::from_millis(40));
+        assert!(timings.write + timings.time_to_first_byte + timings.body_read <= elapsed);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn chunked_response_trailers_are_exposed() {
+        let response = b"HTTP/1.1 200 OK\r\n\
+                         Transfer-Encoding: chunked\r\n\
+                         Trailer: X-Checksum\r\n\r\n\
+                         5;ext=1\r\nhello\r\n\
+                         7\r\n, world\r\n\
+                         0\r\n\
+                         X-Checksum: xor64=000000000000002a\r\n\r\n"
+            .to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_safe(&request).unwrap();
+
+        assert_eq!(res.body, b"hello, world");
+        assert_eq!(res.content_length, Some(12));
+        let trailers = res.trailers();
+        assert_eq!(trailers.len(), 1);
+        assert_eq!(trailers[0].key, "X-Checksum");
+        assert_eq!(trailers[0].value, "xor64=000000000000002a");
+    }
+
+    #[test]
+    fn chunked_response_leaves_the_raw_bytes_as_received() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\nX-Sum: 9\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response: response.clone(), ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(protocol.perform_request_safe(&request).unwrap().body, b"abcde");
+
+        assert_eq!(protocol.last_raw_response(), response);
+        assert_eq!(protocol.last_response_bytes(), response);
+        assert_eq!(protocol.into_body(), b"abcde");
+    }
+
+    #[test]
+    fn pipeline_rejects_an_invalid_request_before_writing_any() {
+        let mut protocol = Http1Protocol::new(CountingTransport::default());
+        let requests = [
+            HttpRequest { method: HttpMethod::Get, path: "/1", body: &[], headers: vec![] },
+            HttpRequest { method: HttpMethod::Get, path: "/2", body: &[], headers: vec![HttpHeaderView { key: "X-Bad", value: "a\r\nb" }] },
+        ];
+
+        let result = protocol.pipeline(&requests);
+
+        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+        assert!(protocol.transport.writes.is_empty());
+    }
+
+    #[test]
+    fn pipeline_failure_after_writing_closes_the_connection() {
+        // Accepts both requests, then closes without answering either.
+        let mut protocol = Http1Protocol::new(FailingSendFileTransport::default());
+        let requests = [
+            HttpRequest { method: HttpMethod::Get, path: "/1", body: &[], headers: vec![] },
+            HttpRequest { method: HttpMethod::Get, path: "/2", body: &[], headers: vec![] },
+        ];
+
+        let result = protocol.pipeline(&requests);
+
+        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::PipelineFailure(0)));
+        assert!(!protocol.is_connected());
+        assert!(!protocol.is_connection_reusable());
+    }
+
+    #[test]
+    fn pipeline_reads_past_a_chunked_response() {
+        // Both responses arrive in a single read, so the second is parsed from the bytes
+        // left over after the first one's last chunk.
+        let responses = [b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nfirst\r\n0\r\n\r\n\
+                           HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond"
+            .to_vec()]
+        .into();
+        let mut protocol = Http1Protocol::new(ScriptedTransport { responses, current: Vec::new() });
+
+        let requests = [
+            HttpRequest { method: HttpMethod::Get, path: "/1", body: &[], headers: vec![] },
+            HttpRequest { method: HttpMethod::Get, path: "/2", body: &[], headers: vec![] },
+        ];
+        let responses = protocol.pipeline(&requests).unwrap();
+
+        assert_eq!(responses[0].body, b"first");
+        assert_eq!(responses[1].body, b"second");
+    }
+
+    #[test]
+    fn unsafe_response_header_lookup_includes_trailers() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nX-Sum: 9\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_unsafe(&request).unwrap();
+
+        assert_eq!(res.header("X-Sum"), Some("9"));
+        let keys: Vec<&str> = res.headers_iter().map(|h| h.key).collect();
+        assert_eq!(keys, ["Transfer-Encoding", "X-Sum"]);
+    }
+
+    #[test]
+    fn chunked_body_is_decoded_when_streamed_to_a_writer() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         5;ext=1\r\nhello\r\n\
+                         7\r\n, world\r\n\
+                         0\r\n\
+                         X-Sum: 12\r\n\r\n"
+            .to_vec();
+        // Small reads split size lines, data and trailers across reads.
+        let transport = ChunkedReadTransport { response, read_pos: 0, chunk: 3, buffer_starts: Vec::new() };
+        let mut protocol = Http1Protocol::new(transport);
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let mut sink = Vec::new();
+        let (head, written) = protocol.perform_request_to_writer(&request, &mut sink).unwrap();
+
+        assert_eq!(sink, b"hello, world");
+        assert_eq!(written, 12);
+        assert_eq!(head.header("X-Sum"), Some("12"));
+        assert_eq!(head.trailers().len(), 1);
+        assert_eq!(head.trailers()[0].key, "X-Sum");
+    }
+
+    #[test]
+    fn streamed_chunked_body_ends_at_the_last_chunk_on_a_kept_alive_connection() {
+        let responses = [
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nfile\r\n0\r\n\r\n".to_vec(),
+            b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nnext".to_vec(),
+        ]
+        .into();
+        let mut protocol = Http1Protocol::new(ScriptedTransport { responses, current: Vec::new() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let mut sink = Vec::new();
+        protocol.perform_request_to_writer(&request, &mut sink).unwrap();
+        assert_eq!(sink, b"file");
+
+        assert_eq!(protocol.perform_request_safe(&request).unwrap().body, b"next");
+    }
+
+    #[test]
+    fn chunked_response_without_trailers() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_safe(&request).unwrap();
+
+        assert_eq!(res.body, b"abc");
+        assert!(res.trailers().is_empty());
+        assert_eq!(res.headers.len(), 1);
+    }
+
+    #[test]
+    fn trailers_are_the_fields_sent_after_the_body() {
+        // X-Late is not announced, and X-Early names a field that only appears up front.
+        let response = b"HTTP/1.1 200 OK\r\n\
+                         Transfer-Encoding: chunked\r\n\
+                         Trailer: X-Early\r\n\
+                         X-Early: 1\r\n\r\n\
+                         3\r\nabc\r\n\
+                         0\r\n\
+                         X-Late: 2\r\n\r\n"
+            .to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_safe(&request).unwrap();
+
+        assert_eq!(res.trailers().len(), 1);
+        assert_eq!(res.trailers()[0].key, "X-Late");
+        assert_eq!(res.header("X-Early"), Some("1"));
+        assert_eq!(res.header("X-Late"), Some("2"));
+    }
+
+    #[test]
+    fn truncated_chunked_response_is_a_parse_failure() {
+        let response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(
+            protocol.perform_request_safe(&request).unwrap_err(),
+            Error::Http(HttpClientError::HttpParseFailure)
+        );
+    }
+
+    #[test]
+    fn status_code_must_be_in_rfc_range() {
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        for (status_line, accepted) in [("099 Low", false), ("600 High", false), ("200 OK", true), ("599 Edge", true)] {
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status_line).into_bytes();
+            let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+
+            let result = protocol.perform_request_safe(&request);
+            if accepted {
+                assert_eq!(result.unwrap().status_message, status_line[4..]);
+            } else {
+                let expected = HttpClientError::MalformedStatusLine { offset: 9, context: format!("HTTP/1.1 {}", status_line) };
+                assert_eq!(result.unwrap_err(), Error::Http(expected), "{}", status_line);
+            }
+        }
+    }
+
+    #[test]
+    fn raw_headers_match_bytes_on_the_wire() {
+        const HEAD: &[u8] = b"HTTP/1.1 200 OK\r\nx-CUSTOM-header:   spaced  \r\ncontent-LENGTH: 2\r\n\r\n";
+        let response = [HEAD, b"ok"].concat();
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+        let mut protocol = Http1Protocol::new(CountingTransport { response: response.clone(), ..Default::default() });
+        assert_eq!(protocol.perform_request_unsafe(&request).unwrap().raw_headers(), HEAD);
+
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        let res = protocol.perform_request_safe(&request).unwrap();
+        assert_eq!(res.raw_headers(), HEAD);
+        assert_eq!(res.body, b"ok");
+    }
+
+    #[test]
+    fn pure_parser_handles_complete_responses() {
+        let res = parse_response(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\nX-Id: 7\r\n\r\nhello").unwrap();
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"hello");
+        assert_eq!(res.header("X-Id"), Some("7"));
+
+        let chunked = parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\nX-Sum: 1\r\n\r\n").unwrap();
+        assert_eq!(chunked.body, b"abc");
+        assert_eq!(chunked.header("X-Sum"), Some("1"));
+        assert_eq!(chunked.trailers()[0].key, "X-Sum");
+
+        let no_content = parse_response(b"HTTP/1.1 204 No Content\r\nContent-Length: 10\r\n\r\n").unwrap();
+        assert!(no_content.body.is_empty());
+    }
+
+    #[test]
+    fn pure_parser_rejects_truncated_input() {
+        let full: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello";
+        for len in 0..full.len() {
+            assert!(parse_response(&full[..len]).is_err(), "prefix of {len} bytes parsed");
+        }
+
+        let chunked: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhel";
+        assert!(parse_response(chunked).is_err());
+    }
+
+    #[test]
+    fn pure_parser_rejects_oversized_lengths() {
+        let huge_length = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\nhi", usize::MAX);
+        assert!(parse_response(huge_length.as_bytes()).is_err());
+
+        let huge_chunk = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nhi\r\n0\r\n\r\n";
+        assert!(parse_response(huge_chunk).is_err());
+    }
+
+    #[test]
+    fn pure_parser_rejects_garbage_without_panicking() {
+        let inputs: [&[u8]; 9] = [
+            b"",
+            b"\n",
+            b"\n\n\n\n",
+            b"\r\n\r\n",
+            b"HTTP/1.1\r\n\r\n",
+            b"HTTP/1.1 abc OK\r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nContent-Length: \r\n\r\n",
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n",
+            b"\xff\xfe\x00\n\n\x80",
+        ];
+        for input in inputs {
+            assert!(parse_response(input).is_err(), "{input:?} parsed");
+        }
+
+        // A cheap deterministic sweep over byte soup built from HTTP-ish fragments.
+        let fragments: [&[u8]; 8] = [b"HTTP/1.1 ", b"200", b" OK", b"\r\n", b"\n", b"Content-Length: 9", b"Transfer-Encoding: chunked", b"0\r\n"];
+        let mut state = 0x2545_f491_u32;
+        for _ in 0..2000 {
+            let mut input = Vec::new();
+            for _ in 0..(state % 12) {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                input.extend_from_slice(fragments[state as usize % fragments.len()]);
+            }
+            let _ = parse_response(&input);
+            state = state.wrapping_add(1);
+        }
+    }
+
+    #[test]
+    fn into_body_reuses_the_read_buffer() {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhelloHTTP/1.1 200 OK\r\n".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        protocol.perform_request_unsafe(&request).unwrap();
+        let buffer_ptr = protocol.get_internal_buffer_ptr_for_test();
+
+        let body = protocol.into_body();
+        assert_eq!(body, b"hello");
+        assert_eq!(body.as_ptr(), buffer_ptr);
+    }
+
+    #[test]
+    fn into_body_is_empty_for_bodiless_responses() {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        let request = HttpRequest { method: HttpMethod::Head, path: "/", body: &[], headers: vec![] };
+        protocol.perform_request_unsafe(&request).unwrap();
+        assert!(protocol.into_body().is_empty());
+    }
+
+    #[test]
+    fn post_file_sends_the_file_contents() {
+        let contents: Vec<u8> = (0..200_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+        let path = std::env::temp_dir().join(format!("httprust_post_file_{}.bin", std::process::id()));
+        std::fs::write(&path, &contents).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut chunk = [0u8; 8192];
+            let body_len = loop {
+                let n = stream.read(&mut chunk).unwrap();
+                received.extend_from_slice(&chunk[..n]);
+                if let Some(header_end) = find_header_end(&received) {
+                    let len = content_length_of(&received[..header_end]).unwrap().unwrap();
+                    break header_end + len;
+                }
+            };
+            while received.len() < body_len {
+                let n = stream.read(&mut chunk).unwrap();
+                received.extend_from_slice(&chunk[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").unwrap();
+            received
+        });
+
+        let mut protocol = Http1Protocol::new(TcpTransport::new());
+        protocol.set_user_agent("");
+        protocol.connect("127.0.0.1", port).unwrap();
+        let headers = [HttpHeaderView { key: "Content-Type", value: "application/octet-stream" }];
+        let status = protocol.post_file("/upload", &path, &headers).unwrap().status_code;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(status, 201);
+        let received = server.join().unwrap();
+        let head = format!(
+            "POST /upload HTTP/1.1\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\n\r\n",
+            contents.len()
+        );
+        assert_eq!(&received[..head.len()], head.as_bytes());
+        assert!(received[head.len()..] == contents[..]);
+    }
+
+    #[test]
+    fn post_file_needs_a_transport_that_can_send_files() {
+        let path = std::env::temp_dir().join(format!("httprust_post_file_unsupported_{}.bin", std::process::id()));
+        std::fs::write(&path, b"data").unwrap();
+
+        let mut protocol = Http1Protocol::new(CountingTransport::default());
+        let result = protocol.post_file("/", &path, &[]).map(|_| ());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(result, Err(Error::Transport(TransportError::Unsupported)));
+        // Turned away before the head went out, so the connection is untouched.
+        assert!(protocol.transport.writes.is_empty());
+        assert!(protocol.is_connection_reusable());
+
+        let missing = std::env::temp_dir().join("httprust_post_file_missing.bin");
+        assert_eq!(protocol.post_file("/", &missing, &[]).map(|_| ()), Err(Error::Http(HttpClientError::FileReadFailure)));
+    }
+
+    // Serves `data` in pieces of at most `piece` bytes. The first read is interrupted by a
+    // signal, and once the data runs out the reader fails if `fail_at_end` is set.
+    struct FlakyReader {
+        data: Vec<u8>,
+        pos: usize,
+        piece: usize,
+        interrupted: bool,
+        fail_at_end: bool,
+    }
+
+    impl FlakyReader {
+        fn new(data: &[u8], piece: usize, fail_at_end: bool) -> Self {
+            Self { data: data.to_vec(), pos: 0, piece, interrupted: false, fail_at_end }
+        }
+    }
+
+    impl Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if !self.interrupted {
+                self.interrupted = true;
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let remaining = &self.data[self.pos..];
+            if remaining.is_empty() && self.fail_at_end {
+                return Err(std::io::Error::other("source went away"));
+            }
+            let n = remaining.len().min(buf.len()).min(self.piece);
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn chunked_upload_retries_interrupted_reads() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        let request = HttpRequest { method: HttpMethod::Post, path: "/", body: &[], headers: vec![] };
+
+        protocol.perform_request_chunked(&request, FlakyReader::new(b"hello world", 5, false)).unwrap();
+        let sent = protocol.transport.writes.concat();
+        assert!(sent.ends_with(b"\r\n\r\n5\r\nhello\r\n5\r\n worl\r\n1\r\nd\r\n0\r\n\r\n"));
+        assert!(protocol.is_connection_reusable());
+    }
+
+    #[test]
+    fn chunked_upload_reader_failure_abandons_the_connection() {
+        let mut protocol = Http1Protocol::new(CountingTransport::default());
+        let request = HttpRequest { method: HttpMethod::Post, path: "/", body: &[], headers: vec![] };
+
+        let result = protocol.perform_request_chunked(&request, FlakyReader::new(b"partial", 64, true)).map(|_| ());
+        assert_eq!(result, Err(Error::Http(HttpClientError::BodyReadFailure)));
+        assert!(!protocol.is_connection_reusable());
+        // The last chunk never went out, so the server cannot mistake this for a full body.
+        assert!(!protocol.transport.writes.concat().ends_with(b"0\r\n\r\n"));
+    }
+
+    // Claims send_file support, then fails partway as a broken connection would.
+    #[derive(Default)]
+    struct FailingSendFileTransport {
+        writes: Vec<Vec<u8>>,
+        closed: bool,
+    }
+
+    impl Transport for FailingSendFileTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_connected(&self) -> bool {
+            !self.closed
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.writes.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Err(Error::Transport(TransportError::ConnectionClosed))
+        }
+
+        fn supports_send_file(&self) -> bool {
+            true
+        }
+
+        fn send_file(&mut self, _fd: RawFd, _offset: u64, _len: u64) -> Result<()> {
+            Err(Error::Transport(TransportError::SocketWriteFailure))
+        }
+
+        fn close(&mut self) -> Result<()> {
+            self.closed = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn post_file_failing_after_the_head_closes_the_connection() {
+        let path = std::env::temp_dir().join(format!("httprust_post_file_failing_{}.bin", std::process::id()));
+        std::fs::write(&path, b"data").unwrap();
+
+        let mut protocol = Http1Protocol::new(FailingSendFileTransport::default());
+        let result = protocol.post_file("/", &path, &[]).map(|_| ());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result, Err(Error::Transport(TransportError::SocketWriteFailure)));
+        assert_eq!(protocol.transport.writes.len(), 1);
+        assert!(!protocol.is_connection_reusable());
+        assert!(!protocol.is_connected());
+    }
+
+    #[test]
+    fn reader_body_with_known_length_is_sent_under_content_length() {
+        let response = b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        let request = HttpRequest { method: HttpMethod::Post, path: "/upload", body: &[], headers: vec![] };
+
+        // Bytes past the declared length stay in the reader; an interrupted read is retried.
+        let body = FlakyReader::new(b"hello world, and more", 4, false);
+        let res = protocol.perform_request_from_reader(&request, body, Some(11)).unwrap();
+        assert_eq!(res.status_code, 201);
+
+        let sent = protocol.transport.writes.concat();
+        let header_end = find_header_end(&sent).unwrap();
+        let head = String::from_utf8_lossy(&sent[..header_end]);
+        assert!(head.contains("Content-Length: 11\r\n"));
+        assert!(!head.contains("Transfer-Encoding"));
+        assert_eq!(&sent[header_end..], b"hello world");
+    }
+
+    #[test]
+    fn reader_body_of_unknown_length_is_sent_chunked() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec();
+        let mut protocol = Http1Protocol::new(CountingTransport { response, ..Default::default() });
+        let request = HttpRequest { method: HttpMethod::Put, path: "/stream", body: &[], headers: vec![] };
+
+        let body = std::io::repeat(b'z').take(20_000);
+        protocol.perform_request_from_reader(&request, body, None).unwrap();
+
+        let sent = protocol.transport.writes.concat();
+        let header_end = find_header_end(&sent).unwrap();
+        let head = String::from_utf8_lossy(&sent[..header_end]);
+        assert!(head.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!head.contains("Content-Length"));
+
+        let chunked = &sent[header_end..];
+        let layout = scan_chunked(chunked).unwrap().unwrap();
+        assert!(layout.chunks.len() > 1);
+        assert_eq!(layout.end, chunked.len());
+        let received: Vec<u8> = layout.chunks.iter().flat_map(|chunk| &chunked[chunk.clone()]).copied().collect();
+        assert_eq!(received, vec![b'z'; 20_000]);
+    }
+
+    #[test]
+    fn reader_body_running_short_or_failing_abandons_the_connection() {
+        let mut protocol = Http1Protocol::new(CountingTransport::default());
+        let request = HttpRequest { method: HttpMethod::Post, path: "/", body: &[], headers: vec![] };
+
+        let result = protocol.perform_request_from_reader(&request, &b"short"[..], Some(20)).map(|_| ());
+        assert_eq!(result, Err(Error::Http(HttpClientError::BodyReadFailure)));
+        assert!(!protocol.is_connection_reusable());
+
+        let mut protocol = Http1Protocol::new(CountingTransport::default());
+        let failing = FlakyReader::new(b"partial", 64, true);
+        let result = protocol.perform_request_from_reader(&request, failing, Some(20)).map(|_| ());
+        assert_eq!(result, Err(Error::Http(HttpClientError::BodyReadFailure)));
+        assert!(!protocol.is_connection_reusable());
+
+        let framed = HttpRequest {
+            method: HttpMethod::Post,
+            path: "/",
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Content-Length", value: "5" }],
+        };
+        let result = protocol.perform_request_from_reader(&framed, &b"short"[..], Some(5)).map(|_| ());
+        assert_eq!(result, Err(Error::Http(HttpClientError::InvalidRequest)));
+    }
+
+    // Takes at most `max_write` bytes per call, and fails the second call as if a signal
+    // arrived before anything was sent.
+    #[derive(Default)]
+    struct ShortWriteTransport {
+        written: Vec<u8>,
+        max_write: usize,
+        calls: usize,
+        response: Vec<u8>,
+        read_pos: usize,
+    }
+
+    impl Transport for ShortWriteTransport {
+        fn connect(&mut self, _host: &str, _port: u16) -> Result<()> {
+            Ok(())
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.calls += 1;
+            if self.calls == 2 {
+                return Err(Error::Transport(TransportError::Interrupted));
+            }
+            let n = buf.len().min(self.max_write);
+            self.written.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let remaining = &self.response[self.read_pos..];
+            if remaining.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
+            let n = remaining.len().min(buf.len());
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.read_pos += n;
+            Ok(n)
+        }
+
+        fn close(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn short_and_interrupted_writes_still_send_every_byte() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec();
+        let body = vec![b'b'; 1000];
+        let request = HttpRequest { method: HttpMethod::Post, path: "/upload", body: &body, headers: vec![] };
+
+        let mut reference = Http1Protocol::new(CountingTransport { response: response.clone(), ..Default::default() });
+        reference.perform_request_safe(&request).unwrap();
+        let expected = reference.transport.writes.concat();
+
+        let mut protocol = Http1Protocol::new(ShortWriteTransport { max_write: 7, response, ..Default::default() });
+        protocol.perform_request_safe(&request).unwrap();
+        assert_eq!(protocol.transport.written, expected);
+        assert!(protocol.transport.calls > expected.len() / 7);
+    }
+
+    #[test]
+    fn status_line_with_empty_head_then_close_is_a_complete_response() {
+        let mut protocol = Http1Protocol::new(CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\n\r\n".to_vec(),
+            ..Default::default()
+        });
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = protocol.perform_request_safe(&request).unwrap();
+        assert_eq!(res.status_code, 200);
+        assert!(res.headers.is_empty());
+        assert!(res.body.is_empty());
+    }
+
+    #[test]
+    fn head_cut_off_before_blank_line_depends_on_lenient_parsing() {
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let respond = |response: &[u8], lenient: bool| {
+            let mut protocol = Http1Protocol::new(CountingTransport { response: response.to_vec(), ..Default::default() });
+            protocol.set_lenient_parsing(lenient);
+            protocol.perform_request_safe(&request)
+        };
+
+        for response in [&b"HTTP/1.1 200 OK\r\n"[..], b"HTTP/1.1 200 OK\r\nX-Id: 7\r\n", b"HTTP/1.1 200 OK\n"] {
+            assert_eq!(respond(response, false).unwrap_err(), Error::Http(HttpClientError::HttpParseFailure));
+
+            let res = respond(response, true).unwrap();
+            assert_eq!(res.status_code, 200);
+            assert!(res.body.is_empty());
+        }
+        assert_eq!(respond(b"HTTP/1.1 200 OK\r\nX-Id: 7\r\n", true).unwrap().header("X-Id"), Some("7"));
+
+        // Still rejected: a partial line, and a head promising a body that never came.
+        assert!(respond(b"HTTP/1.1 200 OK\r\nX-Id", true).is_err());
+        assert!(respond(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n", true).is_err());
+    }
+
+    #[test]
+    fn non_blocking_response_resumes_after_would_block() {
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhe").unwrap();
+            release_rx.recv().unwrap();
+            stream.write_all(b"llo").unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_nonblocking(true).unwrap();
+        let mut protocol = Http1Protocol::new(transport);
+        protocol.connect("127.0.0.1", port).unwrap();
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let mut result = protocol.perform_request_safe(&request);
+        while result == Err(Error::Transport(TransportError::WouldBlock)) && protocol.get_content_length_for_test().is_none() {
+            thread::sleep(Duration::from_millis(1));
+            result = protocol.resume_response().map(into_safe_response);
+        }
+        // The head and part of the body are in; the rest is held back until released.
+        assert_eq!(result, Err(Error::Transport(TransportError::WouldBlock)));
+
+        release_tx.send(()).unwrap();
+        let res = loop {
+            match protocol.resume_response().map(into_safe_response) {
+                Err(Error::Transport(TransportError::WouldBlock)) => thread::sleep(Duration::from_millis(1)),
+                other => break other.unwrap(),
+            }
+        };
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"hello");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn parses_well_formed_status_line() {
+        assert_eq!(
+            parse_status_line(b"HTTP/1.1 404 Not Found\r\n").unwrap(),
+            StatusLine { version: "HTTP/1.1", code: 404, reason: "Not Found" }
+        );
+        assert_eq!(parse_status_line(b"HTTP/1.0 200 OK").unwrap().version, "HTTP/1.0");
+    }
+
+    #[test]
+    fn status_line_reason_is_optional() {
+        for line in [&b"HTTP/1.1 204"[..], b"HTTP/1.1 204 ", b"HTTP/1.1 204\r\n"] {
+            assert_eq!(parse_status_line(line).unwrap(), StatusLine { version: "HTTP/1.1", code: 204, reason: "" });
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_status_lines() {
+        let lines: [(&[u8], usize); 9] = [
+            (b"", 0),
+            (b"HTTP/1.1", 8),
+            (b"HTTX/1.1 200 OK", 0),
+            (b"HTTP/1.1 2OO OK", 9),
+            (b"HTTP/1.1 +20 OK", 9),
+            (b"HTTP/1.1 2000 OK", 9),
+            (b"HTTP/1.1 099 Too Low", 9),
+            (b"HTTP/1.1 600 Too High", 9),
+            (b"HTTP/1.1 200 \xff", 13),
+        ];
+        for (line, expected_offset) in lines {
+            match parse_status_line(line) {
+                Err(Error::Http(HttpClientError::MalformedStatusLine { offset, .. })) => {
+                    assert_eq!(offset, expected_offset, "{line:?}")
+                }
+                other => panic!("{line:?}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn buffered_post_goes_out_in_one_write() {
+        let writes = small_post_with_buffering(true);
+        assert_eq!(writes.len(), 1);
+        assert_eq!(
+            writes[0],
+            b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 7\r\n\r\npayload"
+        );
+    }
+
+    #[test]
+    fn connection_reuse_depends_on_version_and_connection_header() {
+        let cases: [(&[u8], bool); 4] = [
+            (b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n", true),
+            (b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n", false),
+            (b"HTTP/1.0 200 OK\r\nContent-Length: 0\r\n\r\n", false),
+            (b"HTTP/1.0 200 OK\r\nConnection: Keep-Alive\r\n\r\n", true),
+        ];
+
+        for (headers, reusable) in cases {
+            assert_eq!(connection_reusable(headers), reusable, "{}", String::from_utf8_lossy(headers));
+        }
+    }
+
+    #[test]
+    fn protocol_tracks_reusability_of_last_response() {
+        let transport = CountingTransport {
+            response: b"HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        assert!(protocol.is_connection_reusable());
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+        protocol.perform_request_unsafe(&request).unwrap();
+
+        assert!(!protocol.is_connection_reusable());
+    }
+
+    #[test]
+    fn owned_body_outlives_the_protocol_buffer() {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nkeep".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+        let body = protocol.perform_request_unsafe(&request).unwrap().into_owned_body();
+
+        // The borrow on the protocol has ended, so its buffer can be recycled.
+        protocol.reset();
+        assert!(protocol.last_raw_response().is_empty());
+        assert_eq!(body, b"keep");
+    }
+
+    #[test]
+    fn body_flush_size_bounds_each_body_write() {
+        let transport = CountingTransport {
+            response: b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            ..Default::default()
+        };
+        let mut protocol = Http1Protocol::new(transport);
+        protocol.set_body_flush_size(Some(3));
+
+        let request = HttpRequest {
+            method: HttpMethod::Post,
+            path: "/",
+            body: b"abcdefgh",
+            headers: vec![],
+        };
+        protocol.perform_request_unsafe(&request).unwrap();
+
+        let writes = &protocol.transport.writes;
+        assert!(writes[0].ends_with(b"Content-Length: 8\r\n\r\n"));
+        assert_eq!(&writes[1..], &[b"abc".to_vec(), b"def".to_vec(), b"gh".to_vec()]);
+    }
+
+    #[test]
+    fn unbuffered_post_writes_headers_then_body() {
+        let writes = small_post_with_buffering(false);
+        assert_eq!(writes.len(), 2);
+        assert_eq!(
+            writes[0],
+            b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 7\r\n\r\n"
+        );
+        assert_eq!(writes[1], b"payload");
     }
 
     mod tcp_tests {
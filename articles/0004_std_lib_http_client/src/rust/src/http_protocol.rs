@@ -1,10 +1,86 @@
-use crate::error::Result;
+use crate::error::{Error, HttpClientError, Result};
+use crate::decompress::{DecodedBody, decode_content};
+use crate::integrity::{CHECKSUM_HEADER, Checksum, XorChecksum};
+use crate::multipart::MultipartForm;
 use crate::transport::Transport;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, PartialEq)]
+pub const DEFAULT_USER_AGENT: &str = concat!("httprust/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum HttpMethod {
     Get,
+    Head,
     Post,
+    Put,
+    Patch,
+    Delete,
+    Options,
+    Connect,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Connect => "CONNECT",
+        }
+    }
+
+    pub fn allows_body(&self) -> bool {
+        matches!(
+            self,
+            HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch | HttpMethod::Delete
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RequestTarget<'a> {
+    Origin(&'a str),
+    Asterisk,
+    Authority(&'a str),
+    Absolute(&'a str),
+}
+
+impl<'a> RequestTarget<'a> {
+    pub fn from_path(path: &'a str) -> Self {
+        if path == "*" {
+            RequestTarget::Asterisk
+        } else if path.is_empty() {
+            // An empty target would leave a bare double space in the request line.
+            RequestTarget::Origin("/")
+        } else if path.starts_with('/') {
+            RequestTarget::Origin(path)
+        } else if path.contains("://") {
+            RequestTarget::Absolute(path)
+        } else {
+            RequestTarget::Authority(path)
+        }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        match self {
+            RequestTarget::Origin(path) => path,
+            RequestTarget::Asterisk => "*",
+            RequestTarget::Authority(authority) => authority,
+            RequestTarget::Absolute(uri) => uri,
+        }
+    }
+
+    pub fn is_valid_for(&self, method: HttpMethod) -> bool {
+        match self {
+            RequestTarget::Asterisk => method == HttpMethod::Options,
+            RequestTarget::Authority(_) => method == HttpMethod::Connect,
+            RequestTarget::Origin(_) | RequestTarget::Absolute(_) => true,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -27,13 +103,397 @@ pub struct HttpRequest<'a> {
     pub headers: Vec<HttpHeaderView<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct HttpRequestBuilder {
+    method: HttpMethod,
+    path: String,
+    body: Vec<u8>,
+    headers: Vec<HttpOwnedHeader>,
+}
+
+impl HttpRequestBuilder {
+    pub fn new(method: HttpMethod, path: &str) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            body: Vec::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn target(mut self, target: RequestTarget) -> Self {
+        self.path = target.as_str().to_string();
+        self
+    }
+
+    // Appends a percent-encoded `key=value` pair to the target. A path that already
+    // carries a query is extended with `&` rather than given a second `?`.
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        let separator = match self.path.find('?') {
+            None => Some('?'),
+            Some(_) if self.path.ends_with(['?', '&']) => None,
+            Some(_) => Some('&'),
+        };
+        self.path.extend(separator);
+        percent_encode_into(&mut self.path, key);
+        self.path.push('=');
+        percent_encode_into(&mut self.path, value);
+        self
+    }
+
+    // Replaces the target with `path` plus the encoded pairs, so callers never assemble
+    // `?` and `&` by hand. An empty slice leaves just the path.
+    pub fn path_and_query(mut self, path: &str, params: &[(&str, &str)]) -> Self {
+        self.path = path.to_string();
+        params.iter().fold(self, |builder, (key, value)| builder.query(key, value))
+    }
+
+    // Appends rather than replaces, so a repeated key sends one line per call.
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push(HttpOwnedHeader {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+        self
+    }
+
+    pub fn body(mut self, body: &[u8]) -> Self {
+        self.body = body.to_vec();
+        let content_length = self.body.len().to_string();
+        self.set_header("Content-Length", &content_length);
+        self
+    }
+
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::Serialize>(mut self, value: &T) -> Result<Self> {
+        self.body = serde_json::to_vec(value)
+            .map_err(|_| Error::Http(HttpClientError::SerializationFailure))?;
+        let content_length = self.body.len().to_string();
+        self.set_header("Content-Type", "application/json");
+        self.set_header("Content-Length", &content_length);
+        Ok(self)
+    }
+
+    pub fn multipart(mut self, form: MultipartForm) -> Self {
+        let content_length = form.content_length().to_string();
+        self.body = form.body;
+        self.set_header("Content-Type", &form.content_type);
+        self.set_header("Content-Length", &content_length);
+        self
+    }
+
+    // Checksums the body as it stands, so call this after `body` or `json`.
+    pub fn with_integrity(self) -> Self {
+        self.with_integrity_using(&XorChecksum)
+    }
+
+    pub fn with_integrity_using<C: Checksum>(mut self, checksum: &C) -> Self {
+        let value = checksum.header_value(&self.body);
+        self.set_header(CHECKSUM_HEADER, &value);
+        self
+    }
+
+    pub fn build(&self) -> HttpRequest<'_> {
+        HttpRequest {
+            method: self.method,
+            path: &self.path,
+            body: &self.body,
+            headers: self
+                .headers
+                .iter()
+                .map(|h| HttpHeaderView {
+                    key: &h.key,
+                    value: &h.value,
+                })
+                .collect(),
+        }
+    }
+
+    fn set_header(&mut self, key: &str, value: &str) {
+        self.headers.retain(|h| !h.key.eq_ignore_ascii_case(key));
+        self.headers.push(HttpOwnedHeader {
+            key: key.to_string(),
+            value: value.to_string(),
+        });
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct SafeHttpResponse {
     pub status_code: u16,
     pub status_message: String,
     pub body: Vec<u8>,
     pub headers: Vec<HttpOwnedHeader>,
     pub content_length: Option<usize>,
+    // Status line and headers exactly as received, through the blank line.
+    pub raw_head: Vec<u8>,
+    // Fields sent after the last chunk of a chunked body. They are also merged into
+    // `headers`, so `header` finds them either way.
+    pub trailers: Vec<HttpOwnedHeader>,
+}
+
+impl SafeHttpResponse {
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|h| h.key.eq_ignore_ascii_case(key))
+            .map(|h| h.value.as_str())
+    }
+
+    pub fn error_for_status(self) -> Result<Self> {
+        check_status(self.status_code)?;
+        Ok(self)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status_code)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn json<R: serde::de::DeserializeOwned>(&self) -> Result<R> {
+        serde_json::from_slice(&self.body).map_err(|_| Error::Http(HttpClientError::DecodeFailure))
+    }
+
+    // How long a 429 or 503 asks us to wait, from either form of Retry-After. A date
+    // already in the past means no wait; anything unparseable gives None.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.header("Retry-After").and_then(|value| parse_retry_after(value, SystemTime::now()))
+    }
+
+    pub fn raw_headers(&self) -> &[u8] {
+        &self.raw_head
+    }
+
+    pub fn content_type(&self) -> Option<ContentType> {
+        self.header("Content-Type").and_then(ContentType::parse)
+    }
+
+    pub fn content_range(&self) -> Option<ContentRange> {
+        self.header("Content-Range").and_then(ContentRange::parse)
+    }
+
+    // Only the fields that actually arrived after the body, whether or not a `Trailer`
+    // header announced them.
+    pub fn trailers(&self) -> &[HttpOwnedHeader] {
+        &self.trailers
+    }
+
+    // The body with its Content-Encoding removed. Codings this build cannot decode leave
+    // the bytes as received, with `decoded` set to false.
+    pub fn decoded_body(&self) -> Result<DecodedBody> {
+        decode_content(self.header("Content-Encoding"), &self.body)
+    }
+
+    pub fn verify_integrity(&self) -> Result<()> {
+        self.verify_integrity_using(&XorChecksum)
+    }
+
+    pub fn verify_integrity_using<C: Checksum>(&self, checksum: &C) -> Result<()> {
+        match self.header(CHECKSUM_HEADER) {
+            Some(value) if value == checksum.header_value(&self.body) => Ok(()),
+            _ => Err(Error::Http(HttpClientError::IntegrityMismatch)),
+        }
+    }
+
+    // Decodes the body using the `charset` parameter of `Content-Type`, defaulting to UTF-8.
+    // Invalid UTF-8 and unsupported charsets return `DecodeFailure` rather than decoding lossily.
+    pub fn text(&self) -> Result<String> {
+        let content_type = self.content_type();
+        let charset = content_type
+            .as_ref()
+            .and_then(|ct| ct.param("charset"))
+            .unwrap_or("utf-8");
+
+        if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8") {
+            String::from_utf8(self.body.clone())
+                .map_err(|_| Error::Http(HttpClientError::DecodeFailure))
+        } else if ["iso-8859-1", "latin1", "l1"].iter().any(|c| charset.eq_ignore_ascii_case(c)) {
+            Ok(self.body.iter().map(|&b| b as char).collect())
+        } else {
+            Err(Error::Http(HttpClientError::DecodeFailure))
+        }
+    }
+}
+
+// Everything outside RFC 3986's unreserved set is escaped, including `&`, `=` and `+`.
+fn percent_encode_into(out: &mut String, component: &str) {
+    for byte in component.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = parse_http_date(value)?;
+    Some(at.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+// IMF-fixdate only ("Sun, 06 Nov 1994 08:49:37 GMT"), the form RFC 7231 requires senders to use.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    let [weekday, day, month, year, time, "GMT"] = fields[..] else {
+        return None;
+    };
+    if !weekday.ends_with(',') || day.len() != 2 || year.len() != 4 {
+        return None;
+    }
+
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as u64 + 1;
+    let year: u64 = year.parse().ok()?;
+
+    let mut clock = time.split(':').map(|part| part.parse::<u64>().ok().filter(|_| part.len() == 2));
+    let (Some(Some(hour)), Some(Some(minute)), Some(Some(second)), None) =
+        (clock.next(), clock.next(), clock.next(), clock.next())
+    else {
+        return None;
+    };
+    if year < 1970 || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // Days since the epoch for a proleptic Gregorian date (Howard Hinnant's days_from_civil).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    let seconds = days * 86_400 + hour * 3_600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+fn check_status(status_code: u16) -> Result<()> {
+    if (400..600).contains(&status_code) {
+        Err(Error::Http(HttpClientError::UnexpectedStatus(status_code)))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContentType {
+    media_type: String,
+    params: Vec<(String, String)>,
+}
+
+// The parsed value is a media type in RFC 7231 terms; both names refer to the same parser.
+pub type MediaType = ContentType;
+
+impl ContentType {
+    // Type, subtype and parameter names are case-insensitive and stored lowercased;
+    // parameter values keep their case, with quoting and backslash escapes removed.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (media_type, mut rest) = value.split_once(';').unwrap_or((value, ""));
+        let media_type = media_type.trim().to_ascii_lowercase();
+        let (kind, subtype) = media_type.split_once('/')?;
+        if kind.is_empty() || subtype.is_empty() {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ';']);
+            if rest.is_empty() {
+                break;
+            }
+            let (name, after_name) = rest.split_once('=')?;
+            let name = name.trim().to_ascii_lowercase();
+
+            let (value, remainder) = if let Some(quoted) = after_name.strip_prefix('"') {
+                parse_quoted(quoted)?
+            } else {
+                let end = after_name.find(';').unwrap_or(after_name.len());
+                (after_name[..end].trim().to_string(), &after_name[end..])
+            };
+            params.push((name, value));
+            rest = remainder;
+        }
+
+        Some(Self { media_type, params })
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn main_type(&self) -> &str {
+        self.media_type.split_once('/').map_or("", |(main_type, _)| main_type)
+    }
+
+    pub fn subtype(&self) -> &str {
+        self.media_type.split_once('/').map_or("", |(_, subtype)| subtype)
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is(&self, media_type: &str) -> bool {
+        self.media_type.eq_ignore_ascii_case(media_type)
+    }
+}
+
+// The part of the representation a 206 carries: `bytes START-END/TOTAL`, both ends
+// inclusive. TOTAL is None when the server sends `*` because it does not know it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ContentRange {
+    pub start: u64,
+    pub end: u64,
+    pub total: Option<u64>,
+}
+
+impl ContentRange {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (unit, range) = value.trim().split_once(' ')?;
+        if !unit.eq_ignore_ascii_case("bytes") {
+            return None;
+        }
+        let (span, total) = range.trim().split_once('/')?;
+        let (start, end) = span.split_once('-')?;
+        let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+        let total = match total {
+            "*" => None,
+            total => Some(total.parse::<u64>().ok()?),
+        };
+
+        if end < start || total.is_some_and(|total| end >= total) {
+            return None;
+        }
+        Some(Self { start, end, total })
+    }
+
+    pub fn byte_count(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+// Reads a quoted-string whose opening quote is already consumed; returns the unescaped
+// value and whatever follows the closing quote.
+fn parse_quoted(input: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &input[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            _ => value.push(c),
+        }
+    }
+    None
 }
 
 #[derive(Debug, PartialEq)]
@@ -41,15 +501,93 @@ pub struct UnsafeHttpResponse<'a> {
     pub status_code: u16,
     pub status_message: &'a str,
     pub body: &'a [u8],
-    pub headers: Vec<HttpHeaderView<'a>>,
     pub content_length: Option<usize>,
+    // Header lines as received; they are parsed only when asked for.
+    pub header_block: &'a [u8],
+    // Trailer lines from after a chunked body, empty otherwise.
+    pub trailer_block: &'a [u8],
+    pub raw_head: &'a [u8],
+}
+
+impl<'a> UnsafeHttpResponse<'a> {
+    // The head byte for byte as the server sent it, original casing and spacing included.
+    pub fn raw_headers(&self) -> &'a [u8] {
+        self.raw_head
+    }
+
+    // Headers, then any trailers, parsed one line at a time as the iterator advances.
+    pub fn headers_iter(&self) -> std::iter::Chain<HttpHeaderIter<'a>, HttpHeaderIter<'a>> {
+        HttpHeaderIter::new(self.header_block).chain(HttpHeaderIter::new(self.trailer_block))
+    }
+
+    pub fn headers(&self) -> Vec<HttpHeaderView<'a>> {
+        self.headers_iter().collect()
+    }
+
+    pub fn header(&self, key: &str) -> Option<&'a str> {
+        self.headers_iter()
+            .find(|h| h.key.eq_ignore_ascii_case(key))
+            .map(|h| h.value)
+    }
+
+    pub fn error_for_status(self) -> Result<Self> {
+        check_status(self.status_code)?;
+        Ok(self)
+    }
+
+    // Headers borrow the protocol's buffer just as the body does, so a response holding
+    // borrowed headers alongside an owned body would pin the protocol all the same.
+    // Copying out only the body is the useful middle ground.
+    pub fn into_owned_body(self) -> Vec<u8> {
+        self.body.to_vec()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpHeaderIter<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> HttpHeaderIter<'a> {
+    pub fn new(header_block: &'a [u8]) -> Self {
+        Self { remaining: header_block }
+    }
+}
+
+impl<'a> Iterator for HttpHeaderIter<'a> {
+    type Item = HttpHeaderView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.remaining.is_empty() {
+            let (line, rest) = match self.remaining.iter().position(|&b| b == b'\n') {
+                Some(pos) => (&self.remaining[..pos], &self.remaining[pos + 1..]),
+                None => (self.remaining, &self.remaining[self.remaining.len()..]),
+            };
+            self.remaining = rest;
+
+            let line = if line.ends_with(b"\r") { &line[..line.len() - 1] } else { line };
+            if line.is_empty() { continue; }
+
+            let Some(colon_pos) = line.iter().position(|&b| b == b':') else { continue; };
+            let (Ok(key), Ok(value)) = (
+                std::str::from_utf8(&line[..colon_pos]),
+                std::str::from_utf8(&line[colon_pos + 1..]),
+            ) else {
+                continue;
+            };
+
+            // Only the leading OWS is syntax; the rest of the value, empty or not, is kept as sent.
+            return Some(HttpHeaderView { key, value: value.trim_start_matches([' ', '\t']) });
+        }
+        None
+    }
 }
 
 pub trait ParsableResponse<'a>: Sized {
     fn from_parts(
         status_code: u16,
         status_message: &'a str,
-        headers: Vec<HttpHeaderView<'a>>,
+        header_block: &'a [u8],
         body: &'a [u8],
         content_length: Option<usize>,
     ) -> Result<Self>;
@@ -59,15 +597,14 @@ impl<'a> ParsableResponse<'a> for SafeHttpResponse {
     fn from_parts(
         status_code: u16,
         status_message: &'a str,
-        headers: Vec<HttpHeaderView<'a>>,
+        header_block: &'a [u8],
         body: &'a [u8],
         content_length: Option<usize>,
     ) -> Result<Self> {
         Ok(SafeHttpResponse {
             status_code,
             status_message: status_message.to_string(),
-            headers: headers
-                .iter()
+            headers: HttpHeaderIter::new(header_block)
                 .map(|h| HttpOwnedHeader {
                     key: h.key.to_string(),
                     value: h.value.to_string(),
@@ -75,6 +612,8 @@ impl<'a> ParsableResponse<'a> for SafeHttpResponse {
                 .collect(),
             body: body.to_vec(),
             content_length,
+            raw_head: Vec::new(),
+            trailers: Vec::new(),
         })
     }
 }
@@ -83,16 +622,18 @@ impl<'a> ParsableResponse<'a> for UnsafeHttpResponse<'a> {
     fn from_parts(
         status_code: u16,
         status_message: &'a str,
-        headers: Vec<HttpHeaderView<'a>>,
+        header_block: &'a [u8],
         body: &'a [u8],
         content_length: Option<usize>,
     ) -> Result<Self> {
         Ok(UnsafeHttpResponse {
             status_code,
             status_message,
-            headers,
             body,
             content_length,
+            header_block,
+            trailer_block: &[],
+            raw_head: &[],
         })
     }
 }
@@ -104,7 +645,314 @@ pub trait HttpProtocol {
 
     fn disconnect(&mut self) -> Result<()>;
 
-    fn perform_request_unsafe<'a, 'b>(&'a mut self, request: &'b HttpRequest) -> Result<UnsafeHttpResponse<'a>>;
+    fn last_connection_was_reused(&self) -> bool;
+
+    fn set_request_deadline(&mut self, deadline: Option<Duration>);
+
+    // Sent on every request that lacks its own User-Agent header; an empty value sends none.
+    fn set_user_agent(&mut self, user_agent: &str);
+
+    fn perform_request_unsafe<'a>(&'a mut self, request: &HttpRequest) -> Result<UnsafeHttpResponse<'a>>;
+
+    fn perform_request_safe(&mut self, request: &HttpRequest) -> Result<SafeHttpResponse>;
+
+    fn into_transport(self) -> Self::Transport;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_with(content_type: Option<&str>, body: &[u8]) -> SafeHttpResponse {
+        SafeHttpResponse {
+            status_code: 200,
+            status_message: "OK".to_string(),
+            body: body.to_vec(),
+            headers: content_type
+                .map(|value| HttpOwnedHeader { key: "Content-Type".to_string(), value: value.to_string() })
+                .into_iter()
+                .collect(),
+            content_length: Some(body.len()),
+            raw_head: Vec::new(),
+            trailers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn header_iter_preserves_empty_values_and_trailing_whitespace() {
+        let block = b"X-Empty:\r\nX-Space:  value  \r\nNoColonHere\r\nX-Tab:\tv\r\n\r\n";
+        let headers: Vec<HttpHeaderView> = HttpHeaderIter::new(block).collect();
+
+        assert_eq!(
+            headers,
+            vec![
+                HttpHeaderView { key: "X-Empty", value: "" },
+                HttpHeaderView { key: "X-Space", value: "value  " },
+                HttpHeaderView { key: "X-Tab", value: "v" },
+            ]
+        );
+    }
+
+    #[test]
+    fn error_for_status_passes_success_through() {
+        let res = response_with(None, b"ok");
+        assert_eq!(res.error_for_status().unwrap().body, b"ok");
+
+        let mut redirect = response_with(None, b"");
+        redirect.status_code = 302;
+        assert!(redirect.error_for_status().is_ok());
+    }
+
+    #[test]
+    fn error_for_status_converts_client_and_server_errors() {
+        let mut res = response_with(None, b"missing");
+        res.status_code = 404;
+        assert_eq!(
+            res.error_for_status().unwrap_err(),
+            Error::Http(HttpClientError::UnexpectedStatus(404))
+        );
+
+        let unsafe_res = UnsafeHttpResponse {
+            status_code: 503,
+            status_message: "Service Unavailable",
+            body: b"",
+            content_length: Some(0),
+            header_block: b"",
+            trailer_block: b"",
+            raw_head: b"",
+        };
+        assert_eq!(
+            unsafe_res.error_for_status().unwrap_err(),
+            Error::Http(HttpClientError::UnexpectedStatus(503))
+        );
+    }
+
+    #[test]
+    fn with_integrity_attaches_xor_checksum() {
+        let builder = HttpRequestBuilder::new(HttpMethod::Post, "/")
+            .body(b"\x01\x02\x04")
+            .with_integrity();
+        let request = builder.build();
+
+        assert!(request.headers.contains(&HttpHeaderView { key: "X-Checksum", value: "xor64=0000000000000007" }));
+    }
+
+    #[test]
+    fn query_pairs_are_joined_onto_the_path() {
+        let path_only = HttpRequestBuilder::new(HttpMethod::Get, "/search");
+        assert_eq!(path_only.build().path, "/search");
+
+        let one = HttpRequestBuilder::new(HttpMethod::Get, "/search").query("q", "rust");
+        assert_eq!(one.build().path, "/search?q=rust");
+
+        let many = HttpRequestBuilder::new(HttpMethod::Get, "/search")
+            .query("q", "a b&c")
+            .query("lang", "en")
+            .query("tag", "caf\u{e9}=1");
+        assert_eq!(many.build().path, "/search?q=a%20b%26c&lang=en&tag=caf%C3%A9%3D1");
+    }
+
+    #[test]
+    fn query_merges_with_existing_query_string() {
+        let builder = HttpRequestBuilder::new(HttpMethod::Get, "/items?page=2").query("sort", "desc");
+        assert_eq!(builder.build().path, "/items?page=2&sort=desc");
+
+        let trailing = HttpRequestBuilder::new(HttpMethod::Get, "/items?").query("sort", "desc");
+        assert_eq!(trailing.build().path, "/items?sort=desc");
+    }
+
+    fn request_line(builder: &HttpRequestBuilder) -> String {
+        let mut buffer = Vec::new();
+        crate::http1_protocol::write_request_head(&mut buffer, &builder.build(), "").unwrap();
+        let head = String::from_utf8(buffer).unwrap();
+        head.lines().next().unwrap().to_string()
+    }
+
+    fn head_for(method: HttpMethod, path: &str) -> Result<String> {
+        let mut buffer = Vec::new();
+        let request = HttpRequest { method, path, body: &[], headers: vec![] };
+        crate::http1_protocol::write_request_head(&mut buffer, &request, "")?;
+        Ok(String::from_utf8(buffer).unwrap())
+    }
+
+    #[test]
+    fn empty_path_defaults_to_root() {
+        assert_eq!(RequestTarget::from_path(""), RequestTarget::Origin("/"));
+        assert_eq!(request_line(&HttpRequestBuilder::new(HttpMethod::Get, "")), "GET / HTTP/1.1");
+    }
+
+    #[test]
+    fn relative_path_is_rejected() {
+        for path in ["index.html", "api/users", "?q=1"] {
+            assert_eq!(head_for(HttpMethod::Get, path), Err(Error::Http(HttpClientError::InvalidRequest)), "{path}");
+        }
+    }
+
+    #[test]
+    fn asterisk_is_only_valid_for_options() {
+        assert_eq!(head_for(HttpMethod::Options, "*").unwrap(), "OPTIONS * HTTP/1.1\r\n\r\n");
+        assert_eq!(head_for(HttpMethod::Get, "*"), Err(Error::Http(HttpClientError::InvalidRequest)));
+    }
+
+    #[test]
+    fn path_and_query_without_params_sends_bare_path() {
+        let builder = HttpRequestBuilder::new(HttpMethod::Get, "/old").path_and_query("/users", &[]);
+        assert_eq!(request_line(&builder), "GET /users HTTP/1.1");
+    }
+
+    #[test]
+    fn path_and_query_encodes_params() {
+        let builder = HttpRequestBuilder::new(HttpMethod::Get, "/")
+            .path_and_query("/search", &[("q", "a b&c"), ("lang", "en")]);
+        assert_eq!(request_line(&builder), "GET /search?q=a%20b%26c&lang=en HTTP/1.1");
+    }
+
+    #[test]
+    fn path_and_query_extends_existing_query() {
+        let builder = HttpRequestBuilder::new(HttpMethod::Get, "/")
+            .path_and_query("/items?page=2", &[("sort", "desc")]);
+        assert_eq!(request_line(&builder), "GET /items?page=2&sort=desc HTTP/1.1");
+    }
 
-    fn perform_request_safe<'a>(&mut self, request: &'a HttpRequest) -> Result<SafeHttpResponse>;
-}
\ No newline at end of file
+    #[test]
+    fn multipart_sets_body_and_framing_headers() {
+        let form = crate::multipart::MultipartBuilder::with_boundary("b1").text("k", "v").build().unwrap();
+        let expected_length = form.body.len().to_string();
+        let builder = HttpRequestBuilder::new(HttpMethod::Post, "/upload").multipart(form);
+        let request = builder.build();
+
+        assert!(request.body.starts_with(b"--b1\r\n"));
+        assert!(request.headers.contains(&HttpHeaderView { key: "Content-Type", value: "multipart/form-data; boundary=b1" }));
+        assert!(request.headers.contains(&HttpHeaderView { key: "Content-Length", value: &expected_length }));
+    }
+
+    #[test]
+    fn verify_integrity_detects_tampered_body() {
+        let mut res = response_with(None, b"payload");
+        res.headers.push(HttpOwnedHeader {
+            key: "X-Checksum".to_string(),
+            value: XorChecksum.header_value(b"payload"),
+        });
+        assert!(res.verify_integrity().is_ok());
+
+        res.body[0] ^= 0xff;
+        assert_eq!(res.verify_integrity().unwrap_err(), Error::Http(HttpClientError::IntegrityMismatch));
+    }
+
+    #[test]
+    fn verify_integrity_requires_checksum_header() {
+        let res = response_with(None, b"payload");
+        assert_eq!(res.verify_integrity().unwrap_err(), Error::Http(HttpClientError::IntegrityMismatch));
+    }
+
+    #[test]
+    fn retry_after_accepts_delay_seconds() {
+        let mut res = response_with(None, b"");
+        res.headers.push(HttpOwnedHeader { key: "Retry-After".to_string(), value: " 120 ".to_string() });
+        assert_eq!(res.retry_after(), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn retry_after_accepts_http_date() {
+        // 784111777 is the RFC's own example, Sun, 06 Nov 1994 08:49:37 GMT.
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_777 - 90);
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now + Duration::from_secs(3_600)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_http_date("Thu, 29 Feb 2024 23:59:59 GMT"),
+            Some(UNIX_EPOCH + Duration::from_secs(1_709_251_199))
+        );
+    }
+
+    #[test]
+    fn malformed_retry_after_is_ignored() {
+        let now = SystemTime::now();
+        for value in ["", "soon", "-5", "Sunday, 06-Nov-94 08:49:37 GMT", "Sun, 06 Nov 1994 08:49 GMT", "Sun, 06 Foo 1994 08:49:37 GMT"] {
+            assert_eq!(parse_retry_after(value, now), None, "{value:?}");
+        }
+        assert_eq!(response_with(None, b"").retry_after(), None);
+    }
+
+    #[test]
+    fn content_type_splits_media_type_and_charset() {
+        let res = response_with(Some("Text/HTML; Charset=utf-8"), b"");
+        let content_type = res.content_type().unwrap();
+
+        assert_eq!(content_type.media_type(), "text/html");
+        assert_eq!(content_type.subtype(), "html");
+        assert!(content_type.is("TEXT/html"));
+        assert_eq!(content_type.param("charset"), Some("utf-8"));
+        assert_eq!(content_type.param("boundary"), None);
+    }
+
+    #[test]
+    fn content_type_parses_bare_media_type() {
+        let content_type: MediaType = response_with(Some(" Application/JSON "), b"").content_type().unwrap();
+
+        assert_eq!(content_type.media_type(), "application/json");
+        assert_eq!(content_type.main_type(), "application");
+        assert_eq!(content_type.subtype(), "json");
+        assert_eq!(content_type.param("charset"), None);
+    }
+
+    #[test]
+    fn content_range_parses_span_and_total() {
+        let range = ContentRange::parse("bytes 0-99/1000").unwrap();
+        assert_eq!(range, ContentRange { start: 0, end: 99, total: Some(1000) });
+        assert_eq!(range.byte_count(), 100);
+        assert_eq!(ContentRange::parse("bytes 500-999/*").unwrap().total, None);
+
+        for value in ["bytes */1000", "bytes 10-5/100", "bytes 0-100/100", "items 0-1/2", "bytes 0-99"] {
+            assert_eq!(ContentRange::parse(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn content_type_unquotes_parameter_values() {
+        let res = response_with(Some(r#"multipart/form-data; boundary="abc"; note="a \"b\"; c""#), b"");
+        let content_type = res.content_type().unwrap();
+
+        assert_eq!(content_type.media_type(), "multipart/form-data");
+        assert_eq!(content_type.param("boundary"), Some("abc"));
+        assert_eq!(content_type.param("note"), Some(r#"a "b"; c"#));
+    }
+
+    #[test]
+    fn content_type_is_none_when_absent_or_malformed() {
+        assert_eq!(response_with(None, b"").content_type(), None);
+        assert_eq!(response_with(Some("json"), b"").content_type(), None);
+        assert_eq!(response_with(Some("text/plain; charset=\"utf-8"), b"").content_type(), None);
+        assert_eq!(response_with(Some("text/; charset=utf-8"), b"").content_type(), None);
+        assert_eq!(response_with(Some("text/plain; charset"), b"").content_type(), None);
+    }
+
+    #[test]
+    fn text_decodes_utf8_body() {
+        let res = response_with(Some("text/plain; charset=utf-8"), "caf\u{e9} \u{2713}".as_bytes());
+        assert_eq!(res.text().unwrap(), "caf\u{e9} \u{2713}");
+    }
+
+    #[test]
+    fn text_decodes_latin1_body() {
+        let res = response_with(Some("text/plain; Charset=\"ISO-8859-1\""), b"caf\xe9");
+        assert_eq!(res.text().unwrap(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn text_rejects_invalid_utf8_without_charset() {
+        let res = response_with(None, b"caf\xe9");
+        assert_eq!(res.text().unwrap_err(), Error::Http(HttpClientError::DecodeFailure));
+    }
+
+    #[test]
+    fn text_rejects_unsupported_charset() {
+        let res = response_with(Some("text/plain; charset=shift_jis"), b"abc");
+        assert_eq!(res.text().unwrap_err(), Error::Http(HttpClientError::DecodeFailure));
+    }
+}
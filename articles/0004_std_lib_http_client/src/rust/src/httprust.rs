@@ -1,27 +1,129 @@
-use crate::error::{Error, HttpClientError, Result};
+use crate::cache::{CacheLookup, ResponseCache};
+use crate::decompress::supported_encodings;
+use crate::error::{Error, HttpClientError, Result, TransportError};
+use crate::http1_protocol::Http1Protocol;
+use crate::retry::RetryBudget;
+use crate::transport::Transport;
 use crate::http_protocol::{
-    HttpProtocol, HttpMethod, HttpRequest, SafeHttpResponse, UnsafeHttpResponse,
+    ContentRange, HttpHeaderView, HttpProtocol, HttpMethod, HttpRequest, HttpRequestBuilder, SafeHttpResponse,
+    UnsafeHttpResponse,
 };
-use crate::transport::Transport;
 use std::default::Default;
+use std::fs::{self, File};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
 
 pub struct HttpClient<P: HttpProtocol>
 {
     protocol: P,
+    endpoint: Option<(String, u16)>,
+    retry_budget: Option<RetryBudget>,
+    cache: Option<ResponseCache>,
+    decode_content: bool,
+    auto_reconnect: bool,
+    reconnect_non_idempotent: bool,
+}
+
+pub enum Tunnel<T> {
+    Established(T),
+    Rejected(SafeHttpResponse),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RangeResponse {
+    Partial { range: ContentRange, body: Vec<u8> },
+    // The server ignored Range and answered 200 with the whole representation.
+    Full(SafeHttpResponse),
 }
 
 impl<P: HttpProtocol + Default> HttpClient<P>
 {
     pub fn new() -> Self {
-        Self {
-            protocol: P::default(),
-        }
+        Self::with_protocol(P::default())
+    }
+}
+
+impl<P: HttpProtocol + Default> Default for HttpClient<P>
+{
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl<P: HttpProtocol> HttpClient<P>
 {
+    const MAX_RETRIES: usize = 3;
+    const RETRY_BACKOFF: Duration = Duration::from_millis(50);
+    const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+    pub fn with_protocol(protocol: P) -> Self {
+        Self {
+            protocol,
+            endpoint: None,
+            retry_budget: None,
+            cache: None,
+            decode_content: false,
+            auto_reconnect: false,
+            reconnect_non_idempotent: false,
+        }
+    }
+
+    // Idempotent requests sent through the safe helpers are retried on a fresh connection
+    // when it fails, and after a 429 or 503 (waiting out any Retry-After), for as long as
+    // the budget has tokens and at most MAX_RETRIES times each. Without a budget nothing
+    // is retried.
+    pub fn with_retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.retry_budget = Some(budget);
+        self
+    }
+
+    // GET and HEAD requests sent through the safe helpers are answered from `cache` while
+    // fresh, and revalidated with If-None-Match once stale.
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    // With content decoding on, requests sent through the safe helpers advertise the
+    // codings this build can decode in Accept-Encoding, unless they set their own, and
+    // come back with the body decoded and Content-Encoding dropped. A body in any other
+    // coding is returned as received, header and all.
+    pub fn set_decode_content(&mut self, enabled: bool) {
+        self.decode_content = enabled;
+    }
+
+    // When the server has dropped a kept-alive connection since the last response, a
+    // request sent through the safe helpers is sent once more on a new connection instead
+    // of failing. Unlike retries this needs no budget and costs no delay.
+    pub fn set_auto_reconnect(&mut self, enabled: bool) {
+        self.auto_reconnect = enabled;
+    }
+
+    // POST, PATCH and CONNECT are left out of auto-reconnect by default: the server may
+    // have acted on the request before the connection went away.
+    pub fn set_reconnect_non_idempotent(&mut self, allowed: bool) {
+        self.reconnect_non_idempotent = allowed;
+    }
+
+    // Bounds each whole request/response cycle, however steadily the server trickles bytes.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.protocol.set_request_deadline(Some(deadline));
+        self
+    }
+
+    // Requests carry `User-Agent: httprust/<version>` unless they set their own.
+    // Pass a different value to override it, or "" to stop sending the header.
+    pub fn set_user_agent(&mut self, user_agent: &str) {
+        self.protocol.set_user_agent(user_agent);
+    }
+
     pub fn connect(&mut self, host: &str, port: u16) -> Result<()> {
+        self.endpoint = Some((host.to_string(), port));
         self.protocol.connect(host, port)
     }
 
@@ -29,68 +131,511 @@ impl<P: HttpProtocol> HttpClient<P>
         self.protocol.disconnect()
     }
 
-    pub fn get_safe(&mut self, request: &mut HttpRequest) -> Result<SafeHttpResponse> {
-        if !request.body.is_empty() {
-            return Err(Error::Http(HttpClientError::InvalidRequest));
+    pub fn connect_via_proxy(
+        &mut self,
+        proxy_host: &str,
+        proxy_port: u16,
+        target_host: &str,
+        target_port: u16,
+    ) -> Result<()> {
+        self.protocol.connect(proxy_host, proxy_port)?;
+
+        let authority = format!("{}:{}", target_host, target_port);
+        let request = HttpRequest {
+            method: HttpMethod::Connect,
+            path: &authority,
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Host", value: &authority }],
+        };
+
+        let result = match self.protocol.perform_request_unsafe(&request) {
+            Ok(res) => match res.status_code {
+                200..=299 => Ok(()),
+                407 => Err(Error::Http(HttpClientError::ProxyAuthenticationRequired)),
+                _ => Err(Error::Http(HttpClientError::ProxyConnectFailure)),
+            },
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            let _ = self.protocol.disconnect();
+        }
+        result
+    }
+
+    pub fn last_connection_was_reused(&self) -> bool {
+        self.protocol.last_connection_was_reused()
+    }
+
+    // Asks the proxy this client is connected to for a raw tunnel to the target. On 2xx the
+    // transport is handed back for arbitrary bidirectional I/O; bytes the proxy sent
+    // past its response headers are not carried over.
+    pub fn connect_tunnel(mut self, target_host: &str, target_port: u16) -> Result<Tunnel<P::Transport>> {
+        let authority = format!("{}:{}", target_host, target_port);
+        let request = HttpRequest {
+            method: HttpMethod::Connect,
+            path: &authority,
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Host", value: &authority }],
+        };
+
+        let res = self.protocol.perform_request_safe(&request)?;
+        if (200..300).contains(&res.status_code) {
+            Ok(Tunnel::Established(self.protocol.into_transport()))
+        } else {
+            Ok(Tunnel::Rejected(res))
+        }
+    }
+
+    pub fn execute(&mut self, method: HttpMethod, request: &HttpRequest) -> Result<SafeHttpResponse> {
+        Self::validate_request(method, request)?;
+
+        if request.method == method {
+            return self.perform_safe(request);
         }
+        let request = HttpRequest {
+            method,
+            path: request.path,
+            body: request.body,
+            headers: request.headers.clone(),
+        };
+        self.perform_safe(&request)
+    }
+
+    pub fn get_safe(&mut self, request: &mut HttpRequest) -> Result<SafeHttpResponse> {
+        Self::validate_request(HttpMethod::Get, request)?;
         request.method = HttpMethod::Get;
-        self.protocol.perform_request_safe(request)
+        self.perform_safe(request)
     }
 
     pub fn get_unsafe<'a>(
         &'a mut self,
         request: &'a mut HttpRequest,
     ) -> Result<UnsafeHttpResponse<'a>> {
-        if !request.body.is_empty() {
-            return Err(Error::Http(HttpClientError::InvalidRequest));
-        }
+        Self::validate_request(HttpMethod::Get, request)?;
         request.method = HttpMethod::Get;
         self.protocol.perform_request_unsafe(request)
     }
 
+    pub fn head_safe(&mut self, request: &mut HttpRequest) -> Result<SafeHttpResponse> {
+        Self::validate_request(HttpMethod::Head, request)?;
+        request.method = HttpMethod::Head;
+        self.perform_safe(request)
+    }
+
+    pub fn head_unsafe<'a>(
+        &'a mut self,
+        request: &'a mut HttpRequest,
+    ) -> Result<UnsafeHttpResponse<'a>> {
+        Self::validate_request(HttpMethod::Head, request)?;
+        request.method = HttpMethod::Head;
+        self.protocol.perform_request_unsafe(request)
+    }
+
     pub fn post_safe(&mut self, request: &mut HttpRequest) -> Result<SafeHttpResponse> {
-        self.validate_post_request(request)?;
+        Self::validate_request(HttpMethod::Post, request)?;
         request.method = HttpMethod::Post;
-        self.protocol.perform_request_safe(request)
+        self.perform_safe(request)
     }
 
     pub fn post_unsafe<'a>(
         &'a mut self,
         request: &'a mut HttpRequest,
     ) -> Result<UnsafeHttpResponse<'a>> {
-        self.validate_post_request(request)?;
+        Self::validate_request(HttpMethod::Post, request)?;
         request.method = HttpMethod::Post;
         self.protocol.perform_request_unsafe(request)
     }
 
-    fn validate_post_request(&self, request: &HttpRequest) -> Result<()> {
+    fn perform_safe(&mut self, request: &HttpRequest) -> Result<SafeHttpResponse> {
+        if !self.decode_content {
+            return self.perform_cached(request);
+        }
+
+        let accepted = supported_encodings().join(", ");
+        let has_accept_encoding = request.headers.iter().any(|h| h.key.eq_ignore_ascii_case("Accept-Encoding"));
+        let mut res = if accepted.is_empty() || has_accept_encoding {
+            self.perform_cached(request)?
+        } else {
+            let mut headers = request.headers.clone();
+            headers.push(HttpHeaderView { key: "Accept-Encoding", value: &accepted });
+            self.perform_cached(&HttpRequest { method: request.method, path: request.path, body: request.body, headers })?
+        };
+
+        // Bodiless responses to HEAD, 204 and 304 still name the coding they would have used.
+        if res.body.is_empty() || res.header("Content-Encoding").is_none() {
+            return Ok(res);
+        }
+        let decoded = res.decoded_body()?;
+        if decoded.decoded {
+            res.body = decoded.body;
+            res.content_length = Some(res.body.len());
+            res.headers.retain(|h| !h.key.eq_ignore_ascii_case("Content-Encoding"));
+        }
+        Ok(res)
+    }
+
+    fn perform_cached(&mut self, request: &HttpRequest) -> Result<SafeHttpResponse> {
+        let Some(key) = self.cache_key(request) else {
+            return self.perform_with_retries(request);
+        };
+
+        let lookup = self.cache.as_ref().map_or(CacheLookup::Miss, |cache| cache.lookup(&key));
+        let res = match lookup {
+            CacheLookup::Fresh(res) => return Ok(res),
+            CacheLookup::Stale { etag } => {
+                let mut headers = request.headers.clone();
+                headers.push(HttpHeaderView { key: "If-None-Match", value: &etag });
+                let conditional = HttpRequest { method: request.method, path: request.path, body: request.body, headers };
+                self.perform_with_retries(&conditional)?
+            }
+            CacheLookup::Miss => self.perform_with_retries(request)?,
+        };
+
+        if let Some(cache) = &mut self.cache {
+            if res.status_code == 304
+                && let Some(cached) = cache.revalidated(&key, &res)
+            {
+                return Ok(cached);
+            }
+            cache.store(&key, &res);
+        }
+        Ok(res)
+    }
+
+    // Requests that bring their own validators are the caller's business, not the cache's.
+    fn cache_key(&self, request: &HttpRequest) -> Option<String> {
+        let has_validator = request.headers.iter().any(|h| {
+            h.key.eq_ignore_ascii_case("If-None-Match") || h.key.eq_ignore_ascii_case("If-Modified-Since")
+        });
+        if self.cache.is_none() || !ResponseCache::is_cacheable(request.method) || has_validator {
+            return None;
+        }
+
+        let authority = match (request.headers.iter().find(|h| h.key.eq_ignore_ascii_case("Host")), &self.endpoint) {
+            (Some(host), _) => host.value.to_string(),
+            (None, Some((host, port))) => format!("{}:{}", host, port),
+            (None, None) => String::new(),
+        };
+        Some(format!("{} {}{}", request.method.as_str(), authority, request.path))
+    }
+
+    fn perform_with_retries(&mut self, request: &HttpRequest) -> Result<SafeHttpResponse> {
+        let mut result = self.protocol.perform_request_safe(request);
+
+        let idempotent = !matches!(request.method, HttpMethod::Post | HttpMethod::Patch | HttpMethod::Connect);
+        if self.auto_reconnect
+            && (idempotent || self.reconnect_non_idempotent)
+            && self.protocol.last_connection_was_reused()
+            && matches!(
+                result,
+                Err(Error::Transport(TransportError::ConnectionClosed | TransportError::SocketWriteFailure))
+            )
+            && let Some((host, port)) = self.endpoint.clone()
+        {
+            let _ = self.protocol.disconnect();
+            result = self.protocol.connect(&host, port).and_then(|()| self.protocol.perform_request_safe(request));
+        }
+        let mut retries = 0;
+        while idempotent && retries < Self::MAX_RETRIES {
+            // An overloaded server may say when to come back; a failed connection needs replacing.
+            let (retry_after, reconnect) = match &result {
+                Ok(res) if matches!(res.status_code, 429 | 503) => (res.retry_after(), false),
+                Err(err) if is_retryable(err) => (None, true),
+                _ => break,
+            };
+            let endpoint = self.endpoint.clone();
+            if (reconnect && endpoint.is_none())
+                || !self.retry_budget.as_mut().is_some_and(|budget| budget.try_acquire())
+            {
+                break;
+            }
+
+            retries += 1;
+            let backoff = Self::RETRY_BACKOFF * (1 << (retries - 1));
+            thread::sleep(retry_after.unwrap_or(backoff).min(Self::MAX_RETRY_DELAY));
+
+            result = match endpoint {
+                Some((host, port)) if reconnect => {
+                    let _ = self.protocol.disconnect();
+                    self.protocol
+                        .connect(&host, port)
+                        .and_then(|()| self.protocol.perform_request_safe(request))
+                }
+                _ => self.protocol.perform_request_safe(request),
+            };
+        }
+        result
+    }
+
+    // Methods that carry a body must declare it consistently; the rest, DELETE
+    // included, must not send one.
+    fn validate_request(method: HttpMethod, request: &HttpRequest) -> Result<()> {
+        match method {
+            HttpMethod::Post | HttpMethod::Put | HttpMethod::Patch => Self::validate_post_request(request),
+            _ if !request.body.is_empty() => Err(Error::Http(HttpClientError::InvalidRequest)),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_post_request(request: &HttpRequest) -> Result<()> {
         if request.body.is_empty() {
             return Err(Error::Http(HttpClientError::InvalidRequest));
         }
 
-        let content_length_found = request
+        let content_length_header = request
             .headers
             .iter()
-            .any(|h| h.key.eq_ignore_ascii_case("Content-Length"));
+            .find(|h| h.key.eq_ignore_ascii_case("Content-Length"));
 
-        if !content_length_found {
+        let Some(content_length_header) = content_length_header else {
             return Err(Error::Http(HttpClientError::InvalidRequest));
+        };
+
+        let is_chunked = request.headers.iter().any(|h| {
+            h.key.eq_ignore_ascii_case("Transfer-Encoding")
+                && h.value.to_ascii_lowercase().contains("chunked")
+        });
+
+        if !is_chunked {
+            let declared_length = content_length_header
+                .value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Error::Http(HttpClientError::InvalidRequest))?;
+
+            if declared_length != request.body.len() {
+                return Err(Error::Http(HttpClientError::InvalidRequest));
+            }
         }
 
         Ok(())
     }
 }
 
+impl<T: Transport> HttpClient<Http1Protocol<T>>
+{
+    // Only safe and idempotent methods may be pipelined: if the connection drops midway,
+    // every request without a response has to be safe to send again.
+    pub fn pipeline(&mut self, requests: &[HttpRequest]) -> Result<Vec<SafeHttpResponse>> {
+        let all_idempotent = requests.iter().all(|r| {
+            matches!(
+                r.method,
+                HttpMethod::Get | HttpMethod::Head | HttpMethod::Options | HttpMethod::Put | HttpMethod::Delete
+            )
+        });
+        if !all_idempotent {
+            return Err(Error::Http(HttpClientError::InvalidRequest));
+        }
+        for request in requests {
+            Self::validate_request(request.method, request)?;
+        }
+
+        self.protocol.pipeline(requests)
+    }
+
+    // GETs `request` into `path` without holding the body in memory, returning the bytes
+    // written. The body lands in a sibling ".part" file first, so a failed download never
+    // leaves a partial file at `path`.
+    pub fn download_to_file(&mut self, request: &HttpRequest, path: &Path) -> Result<u64> {
+        Self::validate_request(HttpMethod::Get, request)?;
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: request.path,
+            body: request.body,
+            headers: request.headers.clone(),
+        };
+
+        write_atomically(path, |file| {
+            let (res, written) = self.protocol.perform_request_to_writer(&request, file)?;
+            if !(200..300).contains(&res.status_code) {
+                return Err(Error::Http(HttpClientError::UnexpectedStatus(res.status_code)));
+            }
+            Ok(written)
+        })
+    }
+
+    // Connects to `url` (http://host[:port]/path) just for this download, then returns to
+    // the client's own connection, if it had one.
+    pub fn download_url_to_file(&mut self, url: &str, path: &Path) -> Result<u64> {
+        let (host, port, target) = parse_http_url(url)?;
+        let host_header = if port == 80 { host.to_string() } else { format!("{}:{}", host, port) };
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: target,
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Host", value: &host_header }],
+        };
+
+        self.with_connection_to(host, port, |client| client.download_to_file(&request, path))
+    }
+
+    // GETs bytes `start` to `end` (inclusive; None for the rest) of `url` over a connection
+    // opened just for it, e.g. to resume a download. The cache is bypassed, since a stored
+    // full response is not what was asked for.
+    pub fn get_range(&mut self, url: &str, start: u64, end: Option<u64>) -> Result<RangeResponse> {
+        let (host, port, target) = parse_http_url(url)?;
+        let host_header = if port == 80 { host.to_string() } else { format!("{}:{}", host, port) };
+        let range = match end {
+            Some(end) => format!("bytes={}-{}", start, end),
+            None => format!("bytes={}-", start),
+        };
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: target,
+            body: &[],
+            headers: vec![
+                HttpHeaderView { key: "Host", value: &host_header },
+                HttpHeaderView { key: "Range", value: &range },
+            ],
+        };
+        let res = self.with_connection_to(host, port, |client| client.perform_with_retries(&request))?;
+        match res.status_code {
+            206 => {
+                let range = res.content_range().ok_or(Error::Http(HttpClientError::HttpParseFailure))?;
+                Ok(RangeResponse::Partial { range, body: res.body })
+            }
+            200 => Ok(RangeResponse::Full(res)),
+            status => Err(Error::Http(HttpClientError::UnexpectedStatus(status))),
+        }
+    }
+
+    // GETs `url` and decodes the body as text per its charset.
+    pub fn get_text(&mut self, url: &str) -> Result<String> {
+        self.send_to_url(url, |target| Ok(HttpRequestBuilder::new(HttpMethod::Get, target)))?.text()
+    }
+
+    // POSTs `value` as JSON to `url` and decodes the JSON response body into an R.
+    #[cfg(feature = "json")]
+    pub fn post_json<B: serde::Serialize, R: serde::de::DeserializeOwned>(&mut self, url: &str, value: &B) -> Result<R> {
+        self.send_to_url(url, |target| {
+            HttpRequestBuilder::new(HttpMethod::Post, target).header("Accept", "application/json").json(value)
+        })?
+        .json()
+    }
+
+    // Sends the request `build` makes for the target of `url` over a connection opened
+    // just for it. Anything but a 2xx comes back as UnexpectedStatus.
+    fn send_to_url<F>(&mut self, url: &str, build: F) -> Result<SafeHttpResponse>
+    where
+        F: FnOnce(&str) -> Result<HttpRequestBuilder>,
+    {
+        let (host, port, target) = parse_http_url(url)?;
+        let host_header = if port == 80 { host.to_string() } else { format!("{}:{}", host, port) };
+        let builder = build(target)?.header("Host", &host_header);
+        let request = builder.build();
+
+        let res = self.with_connection_to(host, port, |client| client.execute(request.method, &request))?;
+        if !res.is_success() {
+            return Err(Error::Http(HttpClientError::UnexpectedStatus(res.status_code)));
+        }
+        Ok(res)
+    }
+
+    // Runs `send` over a connection to `host:port` opened just for it. That server is the
+    // endpoint meanwhile, so retries and auto-reconnect go back to it; afterwards the
+    // previous endpoint is restored and, if the caller was connected to it, reconnected.
+    fn with_connection_to<R>(&mut self, host: &str, port: u16, send: impl FnOnce(&mut Self) -> Result<R>) -> Result<R> {
+        let previous_endpoint = self.endpoint.take();
+        let was_connected = self.protocol.is_connected();
+        let _ = self.protocol.disconnect();
+
+        let result = self.connect(host, port).and_then(|()| send(self));
+        let _ = self.protocol.disconnect();
+
+        self.endpoint = previous_endpoint;
+        if was_connected && let Some((host, port)) = self.endpoint.clone() {
+            // A failure here surfaces as NotConnected on the caller's next request.
+            let _ = self.protocol.connect(&host, port);
+        }
+        result
+    }
+
+    // Pairs the parsed response with the exact bytes it was parsed from, for audit logs
+    // or replay. The cache is bypassed: a stored response has no wire bytes of its own, and
+    // the protocol buffer would still hold whatever was read last.
+    pub fn get_with_raw(&mut self, request: &HttpRequest) -> Result<(SafeHttpResponse, Vec<u8>)> {
+        Self::validate_request(HttpMethod::Get, request)?;
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: request.path,
+            body: request.body,
+            headers: request.headers.clone(),
+        };
+        let res = self.perform_with_retries(&request)?;
+        Ok((res, self.protocol.last_response_bytes().to_vec()))
+    }
+}
+
+// Failures where the request cannot have been processed, or the connection is gone.
+fn is_retryable(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::Transport(
+            TransportError::ConnectionClosed
+                | TransportError::SocketConnectFailure
+                | TransportError::SocketWriteFailure
+                | TransportError::NotConnected
+                | TransportError::SocketReadFailure
+        )
+    )
+}
+
+fn parse_http_url(url: &str) -> Result<(&str, u16, &str)> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or(Error::Http(HttpClientError::UrlParseFailure))?;
+
+    let (authority, target) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => (
+            host,
+            port.parse::<u16>().map_err(|_| Error::Http(HttpClientError::UrlParseFailure))?,
+        ),
+        _ => (authority, 80),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if host.is_empty() {
+        return Err(Error::Http(HttpClientError::UrlParseFailure));
+    }
+    Ok((host, port, target))
+}
+
+fn write_atomically(path: &Path, fill: impl FnOnce(&mut File) -> Result<u64>) -> Result<u64> {
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".part");
+    let temp_path = Path::new(&temp_name);
+    let write_failure = |_| Error::Http(HttpClientError::FileWriteFailure);
+
+    let result = File::create(temp_path).map_err(write_failure).and_then(|mut file| {
+        let written = fill(&mut file)?;
+        file.sync_all().and_then(|_| fs::rename(temp_path, path)).map_err(write_failure)?;
+        Ok(written)
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(temp_path);
+    }
+    result
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::http1_protocol::Http1Protocol;
     use crate::tcp_transport::TcpTransport;
     use crate::unix_transport::UnixTransport;
+    use crate::cache::ResponseCache;
     use std::io::{Read, Write};
-    use std::net::{Shutdown, TcpListener, TcpStream};
+    use std::net::{TcpListener, TcpStream};
     use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread;
     use std::sync::mpsc;
@@ -148,6 +693,9 @@ mod tests {
         }
     }
 
+    trait ReadWrite: Read + Write {}
+    impl<S: Read + Write> ReadWrite for S {}
+
     fn xor_checksum(data: &[u8]) -> u64 {
         data.iter().fold(0, |acc, &byte| acc ^ u64::from(byte))
     }
@@ -361,88 +909,638 @@ mod tests {
                     client.disconnect().unwrap();
                 }
 
+                #[cfg(feature = "json")]
                 #[test]
-                fn get_request_with_body_returns_error() {
-                    let mut client = HttpClient::<$protocol_struct>::new();
+                fn post_request_with_json_body_succeeds() {
+                    use crate::http_protocol::HttpRequestBuilder;
 
-                    let mut request = HttpRequest {
-                        method: HttpMethod::Get,
-                        path: "/test",
-                        body: b"this body is not allowed",
-                        headers: vec![],
+                    #[derive(serde::Serialize)]
+                    struct Payload {
+                        id: u32,
+                        name: &'static str,
+                    }
+
+                    let canned_response = b"HTTP/1.1 200 OK\r\nContent-Length: 7\r\n\r\nsuccess";
+                    let (tx, rx) = mpsc::channel();
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| {
+                            let mut buffer = vec![0; 1024];
+                            let bytes_read = stream.read(&mut buffer).unwrap();
+                            tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                            stream.write_all(canned_response).unwrap();
+                        })
+                    } else {
+                        setup_unix_server(move |mut stream| {
+                            let mut buffer = vec![0; 1024];
+                            let bytes_read = stream.read(&mut buffer).unwrap();
+                            tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                            stream.write_all(canned_response).unwrap();
+                        })
                     };
 
-                    let result_safe = client.get_safe(&mut request);
-                    assert!(result_safe.is_err());
-                    assert_eq!(
-                        result_safe.unwrap_err(),
-                        Error::Http(HttpClientError::InvalidRequest)
-                    );
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
 
-                    let result_unsafe = client.get_unsafe(&mut request);
-                    assert!(result_unsafe.is_err());
-                    assert_eq!(
-                        result_unsafe.unwrap_err(),
-                        Error::Http(HttpClientError::InvalidRequest)
-                    );
+                    let builder = HttpRequestBuilder::new(HttpMethod::Post, "/json")
+                        .json(&Payload { id: 7, name: "widget" })
+                        .unwrap();
+                    let mut request = builder.build();
+
+                    let result = client.post_safe(&mut request);
+                    assert!(result.is_ok());
+                    assert_eq!(result.unwrap().status_code, 200);
+
+                    let captured_request = rx.recv().unwrap();
+                    let headers_end = captured_request.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+                    let captured_headers = String::from_utf8_lossy(&captured_request[..headers_end]);
+                    let captured_body = &captured_request[headers_end..];
+
+                    assert!(captured_headers.starts_with("POST /json HTTP/1.1\r\n"));
+                    assert!(captured_headers.contains("Content-Type: application/json\r\n"));
+                    assert!(captured_headers.contains(&format!("Content-Length: {}\r\n", captured_body.len())));
+
+                    let value: serde_json::Value = serde_json::from_slice(captured_body).unwrap();
+                    assert_eq!(value["id"], 7);
+                    assert_eq!(value["name"], "widget");
+
+                    client.disconnect().unwrap();
                 }
 
                 #[test]
-                fn post_request_without_body_returns_error() {
+                fn head_request_ignores_declared_content_length() {
+                    let canned_response = b"HTTP/1.1 200 OK\r\nContent-Length: 42\r\n\r\n";
+                    let (tx, rx) = mpsc::channel();
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| {
+                            let mut buffer = vec![0; 1024];
+                            for _ in 0..2 {
+                                let bytes_read = stream.read(&mut buffer).unwrap();
+                                tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                                stream.write_all(canned_response).unwrap();
+                            }
+                        })
+                    } else {
+                        setup_unix_server(move |mut stream| {
+                            let mut buffer = vec![0; 1024];
+                            for _ in 0..2 {
+                                let bytes_read = stream.read(&mut buffer).unwrap();
+                                tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                                stream.write_all(canned_response).unwrap();
+                            }
+                        })
+                    };
+
                     let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
 
                     let mut request = HttpRequest {
-                        method: HttpMethod::Post,
-                        path: "/test",
-                        body: b"",
-                        headers: vec![
-                            HttpHeaderView { key: "Content-Length", value: "0" }
-                        ],
+                        method: HttpMethod::Get,
+                        path: "/resource",
+                        body: &[],
+                        headers: vec![],
                     };
 
-                    let result_safe = client.post_safe(&mut request);
-                    assert!(result_safe.is_err());
-                    assert_eq!(
-                        result_safe.unwrap_err(),
-                        Error::Http(HttpClientError::InvalidRequest)
-                    );
+                    let res = client.head_safe(&mut request).unwrap();
+                    assert_eq!(res.status_code, 200);
+                    assert!(res.body.is_empty());
+                    assert_eq!(res.content_length, Some(42));
 
-                    let result_unsafe = client.post_unsafe(&mut request);
-                    assert!(result_unsafe.is_err());
-                    assert_eq!(
-                        result_unsafe.unwrap_err(),
-                        Error::Http(HttpClientError::InvalidRequest)
-                    );
+                    let captured_request = rx.recv().unwrap();
+                    assert!(String::from_utf8_lossy(&captured_request).starts_with("HEAD /resource HTTP/1.1"));
+
+                    let res = client.head_unsafe(&mut request).unwrap();
+                    assert_eq!(res.status_code, 200);
+                    assert!(res.body.is_empty());
+
+                    client.disconnect().unwrap();
                 }
 
                 #[test]
-                fn post_request_without_content_length_returns_error() {
+                fn head_request_with_body_returns_error() {
                     let mut client = HttpClient::<$protocol_struct>::new();
 
                     let mut request = HttpRequest {
-                        method: HttpMethod::Post,
+                        method: HttpMethod::Head,
                         path: "/test",
-                        body: b"some body",
+                        body: b"not allowed",
                         headers: vec![],
                     };
 
-                    let result_safe = client.post_safe(&mut request);
-                    assert!(result_safe.is_err());
                     assert_eq!(
-                        result_safe.unwrap_err(),
+                        client.head_safe(&mut request).unwrap_err(),
                         Error::Http(HttpClientError::InvalidRequest)
                     );
-
-                    let result_unsafe = client.post_unsafe(&mut request);
-                    assert!(result_unsafe.is_err());
                     assert_eq!(
-                        result_unsafe.unwrap_err(),
+                        client.head_unsafe(&mut request).unwrap_err(),
                         Error::Http(HttpClientError::InvalidRequest)
                     );
                 }
 
                 #[test]
-                fn multi_request_checksum_verification_succeeds() {
+                fn connect_via_proxy_tunnels_subsequent_requests() {
+                    let (tx, rx) = mpsc::channel();
+
+                    let proxy_logic = move |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let bytes_read = stream.read(&mut buffer).unwrap();
+                        tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                        stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").unwrap();
+
+                        let bytes_read = stream.read(&mut buffer).unwrap();
+                        tx.send(buffer[..bytes_read].to_vec()).unwrap();
+                        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\ntunneled").unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| proxy_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| proxy_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.set_user_agent("");
+                    client
+                        .connect_via_proxy(&server_handle.addr, server_handle.port, "example.com", 443)
+                        .unwrap();
+
+                    let captured_connect = rx.recv().unwrap();
+                    assert_eq!(
+                        captured_connect,
+                        b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n"
+                    );
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/inside",
+                        body: &[],
+                        headers: vec![HttpHeaderView { key: "Host", value: "example.com" }],
+                    };
+
+                    let res = client.get_safe(&mut request).unwrap();
+                    assert_eq!(res.status_code, 200);
+                    assert_eq!(res.body, b"tunneled");
+
+                    let captured_request = rx.recv().unwrap();
+                    assert!(String::from_utf8_lossy(&captured_request).starts_with("GET /inside HTTP/1.1"));
+
+                    client.disconnect().unwrap();
+                }
+
+                #[test]
+                fn connect_tunnel_hands_back_transport_for_raw_io() {
+                    let proxy_logic = |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let n = stream.read(&mut buffer).unwrap();
+                        assert!(buffer[..n].starts_with(b"CONNECT db.internal:5432 HTTP/1.1\r\n"));
+                        stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").unwrap();
+
+                        let n = stream.read(&mut buffer).unwrap();
+                        assert_eq!(&buffer[..n], b"ping");
+                        stream.write_all(b"pong").unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| proxy_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| proxy_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let Tunnel::Established(mut transport) = client.connect_tunnel("db.internal", 5432).unwrap() else {
+                        panic!("tunnel was rejected");
+                    };
+
+                    // The std Read/Write traits are also in scope here, hence the qualified calls.
+                    crate::transport::Transport::write(&mut transport, b"ping").unwrap();
+                    let mut buffer = [0u8; 16];
+                    let n = crate::transport::Transport::read(&mut transport, &mut buffer).unwrap();
+                    assert_eq!(&buffer[..n], b"pong");
+                }
+
+                #[test]
+                fn connect_tunnel_returns_rejection_response() {
+                    let proxy_logic = |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream.write_all(b"HTTP/1.1 403 Forbidden\r\nX-Reason: policy\r\n\r\n").unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| proxy_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| proxy_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let Tunnel::Rejected(res) = client.connect_tunnel("db.internal", 5432).unwrap() else {
+                        panic!("tunnel was unexpectedly established");
+                    };
+                    assert_eq!(res.status_code, 403);
+                    assert_eq!(res.header("X-Reason"), Some("policy"));
+                }
+
+                #[test]
+                fn connect_via_proxy_reports_proxy_authentication_required() {
+                    let proxy_logic = |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        stream
+                            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: Basic\r\n\r\n")
+                            .unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| proxy_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| proxy_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    let result = client.connect_via_proxy(&server_handle.addr, server_handle.port, "example.com", 443);
+
+                    assert_eq!(
+                        result.unwrap_err(),
+                        Error::Http(HttpClientError::ProxyAuthenticationRequired)
+                    );
+                }
+
+                #[test]
+                fn deadline_bounds_trickling_response() {
+                    let server_logic = |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let _ = stream.read(&mut buffer).unwrap();
+                        let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n");
+                        for _ in 0..100 {
+                            thread::sleep(Duration::from_millis(20));
+                            if stream.write_all(b"x").is_err() {
+                                break;
+                            }
+                        }
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new().with_deadline(Duration::from_millis(200));
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/slow",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    let started = std::time::Instant::now();
+                    let result = client.get_safe(&mut request);
+
+                    assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::DeadlineExceeded));
+                    assert!(started.elapsed() < Duration::from_secs(1));
+                }
+
+                #[test]
+                fn integrity_checksum_round_trip() {
+                    use crate::http_protocol::HttpRequestBuilder;
+
+                    let server_logic = |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 4096];
+                        let n = stream.read(&mut buffer).unwrap();
+                        let request = String::from_utf8_lossy(&buffer[..n]).to_string();
+                        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+
+                        let received = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("X-Checksum: xor64="))
+                            .unwrap();
+                        let calculated = xor_checksum(body.as_bytes());
+                        assert_eq!(calculated, u64::from_str_radix(received, 16).unwrap(), "Server-side checksum mismatch");
+
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nX-Checksum: xor64={:016x}\r\nContent-Length: {}\r\n\r\n{}",
+                            calculated,
+                            body.len(),
+                            body
+                        );
+                        stream.write_all(response.as_bytes()).unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let builder = HttpRequestBuilder::new(HttpMethod::Post, "/echo")
+                        .body(b"integrity checked payload")
+                        .with_integrity();
+                    let mut request = builder.build();
+
+                    let res = client.post_safe(&mut request).unwrap();
+                    assert_eq!(res.body, b"integrity checked payload");
+                    assert!(res.verify_integrity().is_ok());
+                }
+
+                #[test]
+                fn pipeline_returns_responses_in_request_order() {
+                    let server_logic = |stream: &mut dyn ReadWrite| {
+                        let mut received = Vec::new();
+                        let mut buffer = [0u8; 1024];
+                        while received.windows(4).filter(|w| w == b"\r\n\r\n").count() < 3 {
+                            let n = stream.read(&mut buffer).unwrap();
+                            received.extend_from_slice(&buffer[..n]);
+                        }
+                        stream.write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst\
+                              HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond\
+                              HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nthird"
+                        ).unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let requests: Vec<HttpRequest> = ["/1", "/2", "/3"]
+                        .iter()
+                        .map(|path| HttpRequest { method: HttpMethod::Get, path, body: &[], headers: vec![] })
+                        .collect();
+
+                    let responses = client.pipeline(&requests).unwrap();
+                    let bodies: Vec<&[u8]> = responses.iter().map(|r| r.body.as_slice()).collect();
+                    assert_eq!(bodies, [&b"first"[..], b"second", b"third"]);
+                }
+
+                #[test]
+                fn pipeline_reports_which_response_lost_framing() {
+                    let server_logic = |stream: &mut dyn ReadWrite| {
+                        let mut received = Vec::new();
+                        let mut buffer = [0u8; 1024];
+                        while received.windows(4).filter(|w| w == b"\r\n\r\n").count() < 3 {
+                            let n = stream.read(&mut buffer).unwrap();
+                            received.extend_from_slice(&buffer[..n]);
+                        }
+                        // The second response has no Content-Length, so nothing after it can be split.
+                        stream.write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst\
+                              HTTP/1.1 200 OK\r\n\r\nsecond\
+                              HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nthird"
+                        ).unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let requests: Vec<HttpRequest> = ["/1", "/2", "/3"]
+                        .iter()
+                        .map(|path| HttpRequest { method: HttpMethod::Get, path, body: &[], headers: vec![] })
+                        .collect();
+
+                    let result = client.pipeline(&requests);
+                    assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::PipelineFailure(1)));
+                }
+
+                #[test]
+                fn pipeline_rejects_non_idempotent_methods() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    let requests = [
+                        HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] },
+                        HttpRequest { method: HttpMethod::Post, path: "/", body: b"x", headers: vec![] },
+                    ];
+
+                    let result = client.pipeline(&requests);
+                    assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+                }
+
+                #[test]
+                fn pipeline_validates_every_request() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    let requests = [
+                        HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] },
+                        HttpRequest { method: HttpMethod::Delete, path: "/", body: b"x", headers: vec![] },
+                    ];
+
+                    let result = client.pipeline(&requests);
+                    assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+                }
+
+                #[test]
+                fn execute_sends_request_with_given_method() {
+                    let (tx, rx) = mpsc::channel();
+
+                    let server_logic = move |stream: &mut dyn ReadWrite| {
+                        let mut buffer = vec![0; 1024];
+                        let n = stream.read(&mut buffer).unwrap();
+                        tx.send(buffer[..n].to_vec()).unwrap();
+                        stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+                    let request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/items/7",
+                        body: b"{}",
+                        headers: vec![HttpHeaderView { key: "Content-Length", value: "2" }],
+                    };
+
+                    let res = client.execute(HttpMethod::Put, &request).unwrap();
+                    assert_eq!(res.status_code, 204);
+                    assert!(rx.recv().unwrap().starts_with(b"PUT /items/7 HTTP/1.1\r\n"));
+                }
+
+                #[test]
+                fn execute_applies_method_specific_body_rules() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+
+                    let with_body = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: b"data",
+                        headers: vec![HttpHeaderView { key: "Content-Length", value: "4" }],
+                    };
+                    let without_body = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/",
+                        body: &[],
+                        headers: vec![],
+                    };
+
+                    for method in [HttpMethod::Get, HttpMethod::Head, HttpMethod::Delete] {
+                        assert_eq!(
+                            client.execute(method, &with_body).unwrap_err(),
+                            Error::Http(HttpClientError::InvalidRequest)
+                        );
+                    }
+                    for method in [HttpMethod::Post, HttpMethod::Put, HttpMethod::Patch] {
+                        assert_eq!(
+                            client.execute(method, &without_body).unwrap_err(),
+                            Error::Http(HttpClientError::InvalidRequest)
+                        );
+                    }
+                }
+
+                #[test]
+                fn dropping_client_closes_connection() {
+                    let (tx, rx) = mpsc::channel();
+
+                    let server_logic = move |stream: &mut dyn ReadWrite| {
+                        let mut buffer = [0u8; 16];
+                        tx.send(stream.read(&mut buffer).map_err(|e| e.kind())).unwrap();
+                    };
+
+                    let server_handle = if stringify!($transport_type) == "tcp" {
+                        setup_tcp_server(move |mut stream| server_logic(&mut stream))
+                    } else {
+                        setup_unix_server(move |mut stream| server_logic(&mut stream))
+                    };
+
+                    let mut client = HttpClient::<$protocol_struct>::new();
+                    client.connect(&server_handle.addr, server_handle.port).unwrap();
+                    drop(client);
+
+                    assert_eq!(rx.recv().unwrap(), Ok(0));
+                }
+
+                #[test]
+                fn get_request_with_body_returns_error() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Get,
+                        path: "/test",
+                        body: b"this body is not allowed",
+                        headers: vec![],
+                    };
+
+                    let result_safe = client.get_safe(&mut request);
+                    assert!(result_safe.is_err());
+                    assert_eq!(
+                        result_safe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+
+                    let result_unsafe = client.get_unsafe(&mut request);
+                    assert!(result_unsafe.is_err());
+                    assert_eq!(
+                        result_unsafe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+                }
+
+                #[test]
+                fn post_request_without_body_returns_error() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Post,
+                        path: "/test",
+                        body: b"",
+                        headers: vec![
+                            HttpHeaderView { key: "Content-Length", value: "0" }
+                        ],
+                    };
+
+                    let result_safe = client.post_safe(&mut request);
+                    assert!(result_safe.is_err());
+                    assert_eq!(
+                        result_safe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+
+                    let result_unsafe = client.post_unsafe(&mut request);
+                    assert!(result_unsafe.is_err());
+                    assert_eq!(
+                        result_unsafe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+                }
+
+                #[test]
+                fn post_request_without_content_length_returns_error() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Post,
+                        path: "/test",
+                        body: b"some body",
+                        headers: vec![],
+                    };
+
+                    let result_safe = client.post_safe(&mut request);
+                    assert!(result_safe.is_err());
+                    assert_eq!(
+                        result_safe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+
+                    let result_unsafe = client.post_unsafe(&mut request);
+                    assert!(result_unsafe.is_err());
+                    assert_eq!(
+                        result_unsafe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+                }
+
+                #[test]
+                fn post_request_with_mismatched_content_length_returns_error() {
+                    let mut client = HttpClient::<$protocol_struct>::new();
+
+                    let mut request = HttpRequest {
+                        method: HttpMethod::Post,
+                        path: "/test",
+                        body: b"key=value",
+                        headers: vec![
+                            HttpHeaderView { key: "Content-Length", value: "5" }
+                        ],
+                    };
+
+                    let result_safe = client.post_safe(&mut request);
+                    assert!(result_safe.is_err());
+                    assert_eq!(
+                        result_safe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+
+                    let result_unsafe = client.post_unsafe(&mut request);
+                    assert!(result_unsafe.is_err());
+                    assert_eq!(
+                        result_unsafe.unwrap_err(),
+                        Error::Http(HttpClientError::InvalidRequest)
+                    );
+                }
+
+                #[test]
+                fn multi_request_checksum_verification_succeeds() {
                     const NUM_CYCLES: usize = 50;
 
                     let server_handle = if stringify!($transport_type) == "tcp" {
@@ -452,7 +1550,7 @@ mod tests {
                             let mut bytes_in_buffer = 0;
 
                             for _ in 0..(NUM_CYCLES * 2) {
-                                let mut headers_end = 0;
+                                let headers_end;
                                 let mut content_length = 0;
                                 loop {
                                     let bytes_read = stream.read(&mut buffer[bytes_in_buffer..]).unwrap();
@@ -512,7 +1610,7 @@ mod tests {
                             let mut bytes_in_buffer = 0;
 
                             for _ in 0..(NUM_CYCLES * 2) {
-                                let mut headers_end = 0;
+                                let headers_end;
                                 let mut content_length = 0;
                                 loop {
                                     let bytes_read = stream.read(&mut buffer[bytes_in_buffer..]).unwrap();
@@ -611,6 +1709,649 @@ mod tests {
         };
     }
 
+    fn download_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("httprust_download_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn download_url_to_file_writes_body_and_returns_byte_count() {
+        let (tx, rx) = mpsc::channel();
+        let server_handle = setup_tcp_server(move |mut stream| {
+            let mut buffer = vec![0; 1024];
+            let n = stream.read(&mut buffer).unwrap();
+            tx.send(buffer[..n].to_vec()).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\nfile contents").unwrap();
+        });
+
+        let path = download_path("ok");
+        let _ = std::fs::remove_file(&path);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let url = format!("http://{}:{}/files/report.txt", server_handle.addr, server_handle.port);
+        let written = client.download_url_to_file(&url, &path).unwrap();
+
+        assert_eq!(written, 13);
+        assert_eq!(std::fs::read(&path).unwrap(), b"file contents");
+
+        let captured = String::from_utf8(rx.recv().unwrap()).unwrap();
+        assert!(captured.starts_with("GET /files/report.txt HTTP/1.1\r\n"));
+        assert!(captured.contains(&format!("Host: {}:{}\r\n", server_handle.addr, server_handle.port)));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn download_url_to_file_restores_the_client_connection() {
+        let (download_server, _requests) = setup_scripted_server(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nfile"]);
+        // The caller's own server sees its first connection closed unused, then a new one.
+        let home_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let home_port = home_listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for mut stream in home_listener.incoming().take(2).flatten() {
+                let mut buffer = vec![0; 1024];
+                if stream.read(&mut buffer).unwrap_or(0) > 0 {
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nhome").unwrap();
+                }
+            }
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect("127.0.0.1", home_port).unwrap();
+
+        let path = download_path("restore");
+        let url = format!("http://{}:{}/file", download_server.addr, download_server.port);
+        assert_eq!(client.download_url_to_file(&url, &path).unwrap(), 4);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"home");
+    }
+
+    #[test]
+    fn download_url_to_file_leaves_no_file_on_error_status() {
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found").unwrap();
+        });
+
+        let path = download_path("missing");
+        let _ = std::fs::remove_file(&path);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let url = format!("http://{}:{}/missing", server_handle.addr, server_handle.port);
+        let result = client.download_url_to_file(&url, &path);
+
+        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::UnexpectedStatus(404)));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn download_to_file_streams_large_body() {
+        let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let served = body.clone();
+        let server_handle = setup_tcp_server(move |mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", served.len()).unwrap();
+            for piece in served.chunks(7000) {
+                stream.write_all(piece).unwrap();
+            }
+        });
+
+        let path = download_path("stream");
+        let _ = std::fs::remove_file(&path);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+        let request = HttpRequest { method: HttpMethod::Get, path: "/big.bin", body: &[], headers: vec![] };
+        let written = client.download_to_file(&request, &path).unwrap();
+
+        assert_eq!(written, body.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn download_to_file_decodes_chunked_body() {
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n6\r\nchunk \r\n").unwrap();
+            stream.write_all(b"4\r\nfile\r\n0\r\n\r\n").unwrap();
+            // Stays open, as a kept-alive connection would.
+            let _ = stream.read(&mut buffer);
+        });
+
+        let path = std::env::temp_dir().join(format!("httprust_chunked_download_{}", std::process::id()));
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+
+        assert_eq!(client.download_to_file(&request, &path).unwrap(), 10);
+        assert_eq!(std::fs::read(&path).unwrap(), b"chunk file");
+        std::fs::remove_file(&path).unwrap();
+        client.disconnect().unwrap();
+    }
+
+    #[test]
+    fn get_with_raw_keeps_chunked_framing() {
+        const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nbody\r\n0\r\n\r\n";
+
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(RESPONSE).unwrap();
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let (res, raw) = client.get_with_raw(&request).unwrap();
+        assert_eq!(res.body, b"body");
+        assert_eq!(raw, RESPONSE);
+    }
+
+    #[test]
+    fn parse_http_url_splits_host_port_and_target() {
+        assert_eq!(parse_http_url("http://example.com/a?b=c").unwrap(), ("example.com", 80, "/a?b=c"));
+        assert_eq!(parse_http_url("http://127.0.0.1:8080").unwrap(), ("127.0.0.1", 8080, "/"));
+        assert_eq!(parse_http_url("http://[::1]:9000/x").unwrap(), ("::1", 9000, "/x"));
+        assert_eq!(
+            parse_http_url("https://example.com/").unwrap_err(),
+            Error::Http(HttpClientError::UrlParseFailure)
+        );
+    }
+
+    #[test]
+    fn client_uses_preconfigured_transport() {
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_read_timeout(Some(std::time::Duration::from_secs(2))).unwrap();
+        let mut client = HttpClient::with_protocol(Http1Protocol::new(transport));
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+
+        let res = client.get_safe(&mut request).unwrap();
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"ok");
+    }
+
+    // Serves one response per connection and then closes it, without saying so up front.
+    fn setup_one_shot_server() -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let accepted_by_server = Arc::clone(&accepted);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let connection = accepted_by_server.fetch_add(1, Ordering::SeqCst) + 1;
+                let mut buffer = vec![0; 1024];
+                let _ = stream.read(&mut buffer).unwrap();
+                let body = format!("conn{}", connection);
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+        (port, accepted)
+    }
+
+    #[test]
+    fn auto_reconnect_replaces_connection_closed_by_server() {
+        let (port, accepted) = setup_one_shot_server();
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.set_auto_reconnect(true);
+        client.connect("127.0.0.1", port).unwrap();
+
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"conn1");
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"conn2");
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn auto_reconnect_leaves_post_alone_unless_allowed() {
+        let (port, accepted) = setup_one_shot_server();
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.set_auto_reconnect(true);
+        client.connect("127.0.0.1", port).unwrap();
+
+        let headers = vec![HttpHeaderView { key: "Content-Length", value: "1" }];
+        let mut request = HttpRequest { method: HttpMethod::Post, path: "/", body: b"x", headers };
+        assert_eq!(client.post_safe(&mut request).unwrap().body, b"conn1");
+        thread::sleep(Duration::from_millis(20));
+        assert!(client.post_safe(&mut request).is_err());
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+
+        client.connect("127.0.0.1", port).unwrap();
+        client.set_reconnect_non_idempotent(true);
+        assert_eq!(client.post_safe(&mut request).unwrap().body, b"conn2");
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(client.post_safe(&mut request).unwrap().body, b"conn3");
+    }
+
+    #[test]
+    fn exhausted_retry_budget_fails_fast() {
+
+        // Accepts connections and drops them straight away, like a server mid-restart.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let accepted_by_server = Arc::clone(&accepted);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                accepted_by_server.fetch_add(1, Ordering::SeqCst);
+                drop(stream);
+            }
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new()
+            .with_retry_budget(RetryBudget::new(2, 0.0));
+        client.connect("127.0.0.1", port).unwrap();
+
+        let mut request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+
+        // The first failure spends both tokens: the original connection plus two retries.
+        assert!(client.get_safe(&mut request).is_err());
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(accepted.load(Ordering::SeqCst), 3);
+
+        // With the budget empty, further failures are returned without reconnecting.
+        for _ in 0..3 {
+            assert!(client.get_safe(&mut request).is_err());
+        }
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(accepted.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn retry_honors_retry_after_on_503() {
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 1\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new()
+            .with_retry_budget(RetryBudget::new(1, 0.0));
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+
+        let started = std::time::Instant::now();
+        let res = client.get_safe(&mut request).unwrap();
+        assert_eq!(res.status_code, 200);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn get_with_raw_returns_response_bytes_verbatim() {
+        const RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nX-Audit:  kept as sent \r\nContent-Length: 4\r\n\r\nbody";
+
+        let server_handle = setup_tcp_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(RESPONSE).unwrap();
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+
+        let (res, raw) = client.get_with_raw(&request).unwrap();
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"body");
+        assert_eq!(raw, RESPONSE);
+    }
+
+    #[test]
+    fn client_sends_configured_user_agent() {
+        let (tx, rx) = mpsc::channel();
+        let server_handle = setup_tcp_server(move |mut stream| {
+            let mut buffer = vec![0; 1024];
+            let n = stream.read(&mut buffer).unwrap();
+            tx.send(buffer[..n].to_vec()).unwrap();
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.set_user_agent("monitor/1.4");
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+        client.get_safe(&mut request).unwrap();
+
+        let captured = String::from_utf8(rx.recv().unwrap()).unwrap();
+        assert!(captured.contains("User-Agent: monitor/1.4\r\n"));
+    }
+
+    // Answers successive requests on one connection with `responses`, reporting each
+    // request it receives.
+    fn setup_scripted_server(responses: Vec<&'static [u8]>) -> (ServerHandle, mpsc::Receiver<String>) {
+        let (tx, rx) = mpsc::channel();
+        let server_handle = setup_tcp_server(move |mut stream| {
+            let mut buffer = vec![0; 1024];
+            for response in responses {
+                let n = stream.read(&mut buffer).unwrap();
+                if n == 0 {
+                    return;
+                }
+                tx.send(String::from_utf8_lossy(&buffer[..n]).into_owned()).unwrap();
+                stream.write_all(response).unwrap();
+            }
+        });
+        (server_handle, rx)
+    }
+
+    fn get_twice(client: &mut HttpClient<Http1Protocol<TcpTransport>>) -> (SafeHttpResponse, SafeHttpResponse) {
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/resource", body: &[], headers: vec![] };
+        let first = client.get_safe(&mut request).unwrap();
+        let second = client.get_safe(&mut request).unwrap();
+        (first, second)
+    }
+
+    #[test]
+    fn get_range_returns_partial_content() {
+        let response = format!(
+            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-99/1000\r\nContent-Length: 100\r\n\r\n{}",
+            "a".repeat(100)
+        );
+        let (server_handle, requests) = setup_scripted_server(vec![response.into_bytes().leak()]);
+        let url = format!("http://{}:{}/file.bin", server_handle.addr, server_handle.port);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let RangeResponse::Partial { range, body } = client.get_range(&url, 0, Some(99)).unwrap() else {
+            panic!("expected a partial response");
+        };
+        assert_eq!(range, ContentRange { start: 0, end: 99, total: Some(1000) });
+        assert_eq!(body.len() as u64, range.byte_count());
+        assert!(requests.recv().unwrap().contains("\r\nRange: bytes=0-99\r\n"));
+    }
+
+    #[test]
+    fn get_range_retries_against_the_range_host_and_restores_the_connection() {
+        // The range server drops its first connection unanswered, so the request is retried.
+        let range_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let range_port = range_listener.local_addr().unwrap().port();
+        let range_server = thread::spawn(move || {
+            let (first, _) = range_listener.accept().unwrap();
+            drop(first);
+            let (mut second, _) = range_listener.accept().unwrap();
+            let mut buffer = vec![0; 1024];
+            let n = second.read(&mut buffer).unwrap();
+            second
+                .write_all(b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-3/10\r\nContent-Length: 4\r\n\r\nabcd")
+                .unwrap();
+            String::from_utf8_lossy(&buffer[..n]).into_owned()
+        });
+        // The caller's own server sees its first connection closed unused, then a new one.
+        let home_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let home_port = home_listener.local_addr().unwrap().port();
+        let (home_tx, home_requests) = mpsc::channel();
+        thread::spawn(move || {
+            for mut stream in home_listener.incoming().take(2).flatten() {
+                let mut buffer = vec![0; 1024];
+                let n = stream.read(&mut buffer).unwrap_or(0);
+                if n > 0 {
+                    home_tx.send(String::from_utf8_lossy(&buffer[..n]).into_owned()).unwrap();
+                    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nhome").unwrap();
+                }
+            }
+        });
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_retry_budget(RetryBudget::new(2, 0.0));
+        client.connect("127.0.0.1", home_port).unwrap();
+
+        let url = format!("http://127.0.0.1:{}/file.bin", range_port);
+        let RangeResponse::Partial { body, .. } = client.get_range(&url, 0, Some(3)).unwrap() else {
+            panic!("expected a partial response");
+        };
+        assert_eq!(body, b"abcd");
+        assert!(range_server.join().unwrap().contains("\r\nRange: bytes=0-3\r\n"));
+
+        // The caller's own connection is back in place for the next request.
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"home");
+        assert!(home_requests.recv().unwrap().starts_with("GET / HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn get_text_returns_decoded_body() {
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=iso-8859-1\r\nContent-Length: 5\r\n\r\ncaf\xe9!",
+        ]);
+        let url = format!("http://{}:{}/menu", server_handle.addr, server_handle.port);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        assert_eq!(client.get_text(&url).unwrap(), "caf\u{e9}!");
+        assert!(requests.recv().unwrap().starts_with("GET /menu HTTP/1.1\r\n"));
+    }
+
+    #[test]
+    fn get_text_retries_against_the_url_host() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (first, _) = listener.accept().unwrap();
+            drop(first);
+            let (mut second, _) = listener.accept().unwrap();
+            let mut buffer = vec![0; 1024];
+            let _ = second.read(&mut buffer).unwrap();
+            second.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nretry").unwrap();
+        });
+
+        // No endpoint of its own: retries can only work if the URL's host is recorded.
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_retry_budget(RetryBudget::new(2, 0.0));
+        assert_eq!(client.get_text(&format!("http://127.0.0.1:{}/", port)).unwrap(), "retry");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn get_text_reports_unexpected_status() {
+        let (server_handle, _requests) =
+            setup_scripted_server(vec![b"HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found"]);
+        let url = format!("http://{}:{}/missing", server_handle.addr, server_handle.port);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        assert_eq!(client.get_text(&url), Err(Error::Http(HttpClientError::UnexpectedStatus(404))));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn post_json_round_trips_typed_values() {
+        #[derive(serde::Serialize)]
+        struct Order {
+            item: &'static str,
+            quantity: u32,
+        }
+
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Receipt {
+            id: u64,
+            total_cents: u64,
+        }
+
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: 28\r\n\r\n{\"id\":42,\"total_cents\":1500}",
+        ]);
+        let url = format!("http://{}:{}/orders", server_handle.addr, server_handle.port);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let receipt: Receipt = client.post_json(&url, &Order { item: "widget", quantity: 3 }).unwrap();
+        assert_eq!(receipt, Receipt { id: 42, total_cents: 1500 });
+
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("POST /orders HTTP/1.1\r\n"));
+        assert!(request.contains("\r\nContent-Type: application/json\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"item\":\"widget\",\"quantity\":3}"));
+    }
+
+    #[test]
+    fn get_range_reports_server_ignoring_the_range() {
+        let (server_handle, requests) = setup_scripted_server(vec![b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\neverything"]);
+        let url = format!("http://{}:{}/file.bin", server_handle.addr, server_handle.port);
+
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        let RangeResponse::Full(res) = client.get_range(&url, 500, None).unwrap() else {
+            panic!("expected the full response");
+        };
+        assert_eq!(res.body, b"everythi");
+        assert!(requests.recv().unwrap().contains("\r\nRange: bytes=500-\r\n"));
+    }
+
+    #[test]
+    fn fresh_cached_response_skips_the_network() {
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nContent-Length: 5\r\n\r\nfirst",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond",
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_cache(ResponseCache::new());
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let (first, second) = get_twice(&mut client);
+        assert_eq!(first.body, b"first");
+        assert_eq!(second, first);
+        assert_eq!(requests.try_iter().count(), 1);
+    }
+
+    #[test]
+    fn stale_entry_is_revalidated_with_if_none_match() {
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=0\r\nETag: \"v1\"\r\nContent-Length: 5\r\n\r\nfirst",
+            b"HTTP/1.1 304 Not Modified\r\nCache-Control: max-age=60\r\nETag: \"v1\"\r\n\r\n",
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_cache(ResponseCache::new());
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let (first, second) = get_twice(&mut client);
+        assert_eq!(second.status_code, 200);
+        assert_eq!(second.body, first.body);
+
+        let requests: Vec<String> = requests.try_iter().collect();
+        assert_eq!(requests.len(), 2);
+        assert!(!requests[0].contains("If-None-Match"));
+        assert!(requests[1].contains("If-None-Match: \"v1\"\r\n"));
+
+        // The 304 refreshed the entry, so a third request stays local.
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/resource", body: &[], headers: vec![] };
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"first");
+    }
+
+    #[test]
+    fn get_with_raw_bypasses_a_fresh_cache_entry() {
+        const SECOND: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond";
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nCache-Control: max-age=60\r\nContent-Length: 5\r\n\r\nfirst",
+            SECOND,
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_cache(ResponseCache::new());
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/resource", body: &[], headers: vec![] };
+        assert_eq!(client.get_safe(&mut request).unwrap().body, b"first");
+
+        let (res, raw) = client.get_with_raw(&request).unwrap();
+        assert_eq!(res.body, b"second");
+        assert_eq!(raw, SECOND);
+        assert_eq!(requests.try_iter().count(), 2);
+    }
+
+    #[cfg(feature = "br")]
+    #[test]
+    fn decode_content_negotiates_and_decodes_brotli() {
+        // The body is "brotli over the wire" as a single uncompressed brotli meta-block.
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Encoding: br\r\nContent-Length: 24\r\n\r\n\x8b\x09\x80brotli over the wire\x03",
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.set_decode_content(true);
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+        let res = client.get_safe(&mut request).unwrap();
+
+        assert_eq!(res.body, b"brotli over the wire");
+        assert_eq!(res.content_length, Some(20));
+        assert_eq!(res.header("Content-Encoding"), None);
+        let sent = requests.recv().unwrap();
+        assert!(sent.lines().any(|line| line.starts_with("Accept-Encoding: ") && line.contains("br")));
+    }
+
+    #[test]
+    fn decode_content_leaves_unknown_codings_as_received() {
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nContent-Encoding: zstd\r\nContent-Length: 6\r\n\r\nopaque",
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new();
+        client.set_decode_content(true);
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let mut request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![HttpHeaderView { key: "Accept-Encoding", value: "zstd" }],
+        };
+        let res = client.get_safe(&mut request).unwrap();
+
+        assert_eq!(res.body, b"opaque");
+        assert_eq!(res.header("Content-Encoding"), Some("zstd"));
+        assert!(requests.recv().unwrap().contains("\r\nAccept-Encoding: zstd\r\n"));
+    }
+
+    #[test]
+    fn no_store_response_is_not_cached() {
+        let (server_handle, requests) = setup_scripted_server(vec![
+            b"HTTP/1.1 200 OK\r\nCache-Control: no-store, max-age=60\r\nContent-Length: 5\r\n\r\nfirst",
+            b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond",
+        ]);
+        let mut client = HttpClient::<Http1Protocol<TcpTransport>>::new().with_cache(ResponseCache::new());
+        client.connect(&server_handle.addr, server_handle.port).unwrap();
+
+        let (_, second) = get_twice(&mut client);
+        assert_eq!(second.body, b"second");
+        assert_eq!(requests.try_iter().count(), 2);
+        assert!(client.cache().unwrap().is_empty());
+    }
+
     generate_http_client_tests!(tcp, TcpTransport, Http1Protocol<TcpTransport>);
     generate_http_client_tests!(unix, UnixTransport, Http1Protocol<UnixTransport>);
 }
\ No newline at end of file
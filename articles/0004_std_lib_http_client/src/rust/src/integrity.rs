@@ -0,0 +1,26 @@
+pub const CHECKSUM_HEADER: &str = "X-Checksum";
+
+pub trait Checksum {
+    fn algorithm(&self) -> &'static str;
+
+    fn digest(&self, body: &[u8]) -> String;
+
+    fn header_value(&self, body: &[u8]) -> String {
+        format!("{}={}", self.algorithm(), self.digest(body))
+    }
+}
+
+// The same fold the benchmark appends to every payload.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct XorChecksum;
+
+impl Checksum for XorChecksum {
+    fn algorithm(&self) -> &'static str {
+        "xor64"
+    }
+
+    fn digest(&self, body: &[u8]) -> String {
+        let checksum = body.iter().fold(0u64, |acc, &byte| acc ^ u64::from(byte));
+        format!("{:016x}", checksum)
+    }
+}
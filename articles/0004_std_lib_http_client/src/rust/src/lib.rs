@@ -1,14 +1,42 @@
 pub mod error;
 pub mod transport;
+pub mod resolver;
 pub mod tcp_transport;
 pub mod unix_transport;
+pub mod generic_transport;
+#[cfg(feature = "tls")]
+pub mod tls_transport;
+pub mod integrity;
+pub mod decompress;
+pub mod multipart;
+pub mod retry;
+pub mod cache;
+pub mod cookie;
 pub mod http_protocol;
 pub mod http1_protocol;
 pub mod httprust;
+#[cfg(feature = "async")]
+pub mod async_transport;
+#[cfg(feature = "async")]
+pub mod async_http1_protocol;
 
 pub use transport::Transport;
+pub use resolver::{Resolver, SystemResolver};
 pub use tcp_transport::TcpTransport;
 pub use unix_transport::UnixTransport;
-pub use http_protocol::{HttpProtocol, HttpMethod, HttpRequest, HttpHeaderView, SafeHttpResponse, UnsafeHttpResponse};
-pub use http1_protocol::Http1Protocol;
-pub use httprust::HttpClient;
\ No newline at end of file
+pub use generic_transport::{GenericTransport, StdIoTransport};
+#[cfg(feature = "tls")]
+pub use tls_transport::TlsTransport;
+pub use integrity::{Checksum, XorChecksum};
+pub use decompress::DecodedBody;
+pub use multipart::{MultipartBuilder, MultipartForm};
+pub use retry::RetryBudget;
+pub use cache::ResponseCache;
+pub use cookie::{Cookie, SameSite};
+pub use http_protocol::{ContentRange, ContentType, MediaType, HttpProtocol, HttpMethod, HttpRequest, HttpRequestBuilder, HttpHeaderView, HttpHeaderIter, RequestTarget, SafeHttpResponse, UnsafeHttpResponse};
+pub use http1_protocol::{parse_response, parse_status_line, Http1Protocol, RequestTimings, StatusLine};
+pub use httprust::{HttpClient, RangeResponse, Tunnel};
+#[cfg(feature = "async")]
+pub use async_transport::{AsyncTransport, AsyncTcpTransport, AsyncUnixTransport};
+#[cfg(feature = "async")]
+pub use async_http1_protocol::AsyncHttp1Protocol;
@@ -0,0 +1,180 @@
+use crate::error::{Error, HttpClientError, Result};
+use crate::http_protocol::HttpOwnedHeader;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static BOUNDARY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, PartialEq, Clone)]
+struct Part {
+    headers: Vec<HttpOwnedHeader>,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultipartForm {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl MultipartForm {
+    pub fn content_length(&self) -> usize {
+        self.body.len()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultipartBuilder {
+    boundary: Option<String>,
+    parts: Vec<Part>,
+}
+
+impl Default for MultipartBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartBuilder {
+    // The boundary is picked at build time, once all the data is known.
+    pub fn new() -> Self {
+        Self { boundary: None, parts: Vec::new() }
+    }
+
+    // A fixed boundary; build fails if it turns up inside any part.
+    pub fn with_boundary(boundary: &str) -> Self {
+        Self { boundary: Some(boundary.to_string()), parts: Vec::new() }
+    }
+
+    pub fn text(self, name: &str, value: &str) -> Self {
+        let disposition = format!("form-data; name=\"{}\"", escape_quoted(name));
+        self.part(&[("Content-Disposition", &disposition)], value.as_bytes())
+    }
+
+    pub fn file(self, name: &str, filename: &str, content_type: &str, data: &[u8]) -> Self {
+        let disposition = format!(
+            "form-data; name=\"{}\"; filename=\"{}\"",
+            escape_quoted(name),
+            escape_quoted(filename)
+        );
+        self.part(&[("Content-Disposition", &disposition), ("Content-Type", content_type)], data)
+    }
+
+    pub fn part(mut self, headers: &[(&str, &str)], data: &[u8]) -> Self {
+        self.parts.push(Part {
+            headers: headers
+                .iter()
+                .map(|(key, value)| HttpOwnedHeader { key: key.to_string(), value: value.to_string() })
+                .collect(),
+            data: data.to_vec(),
+        });
+        self
+    }
+
+    pub fn build(&self) -> Result<MultipartForm> {
+        let has_line_break = |s: &str| s.bytes().any(|b| b == b'\r' || b == b'\n');
+        if self.parts.iter().flat_map(|p| &p.headers).any(|h| has_line_break(&h.key) || has_line_break(&h.value)) {
+            return Err(Error::Http(HttpClientError::InvalidRequest));
+        }
+
+        let boundary = match &self.boundary {
+            Some(boundary) if self.collides(boundary) => {
+                return Err(Error::Http(HttpClientError::InvalidRequest));
+            }
+            Some(boundary) => boundary.clone(),
+            None => loop {
+                let candidate = generate_boundary();
+                if !self.collides(&candidate) {
+                    break candidate;
+                }
+            },
+        };
+
+        let mut body = Vec::with_capacity(self.parts.iter().map(|p| p.data.len() + 128).sum());
+        for part in &self.parts {
+            body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            for header in &part.headers {
+                body.extend_from_slice(format!("{}: {}\r\n", header.key, header.value).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+        Ok(MultipartForm {
+            content_type: format!("multipart/form-data; boundary={}", boundary),
+            body,
+        })
+    }
+
+    fn collides(&self, boundary: &str) -> bool {
+        let delimiter = format!("--{}", boundary);
+        self.parts.iter().any(|part| {
+            part.data.windows(delimiter.len()).any(|window| window == delimiter.as_bytes())
+        })
+    }
+}
+
+fn generate_boundary() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let count = BOUNDARY_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mixed = (nanos ^ count.wrapping_mul(0x9e37_79b9_7f4a_7c15)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    format!("httprust-{:016x}", mixed)
+}
+
+fn escape_quoted(value: &str) -> String {
+    value.replace('"', "%22")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_two_part_form() {
+        let form = MultipartBuilder::with_boundary("XyZ")
+            .text("title", "report")
+            .file("upload", "data.csv", "text/csv", b"a,b\n1,2\n")
+            .build()
+            .unwrap();
+
+        assert_eq!(form.content_type, "multipart/form-data; boundary=XyZ");
+        assert_eq!(
+            form.body,
+            b"--XyZ\r\n\
+              Content-Disposition: form-data; name=\"title\"\r\n\
+              \r\n\
+              report\r\n\
+              --XyZ\r\n\
+              Content-Disposition: form-data; name=\"upload\"; filename=\"data.csv\"\r\n\
+              Content-Type: text/csv\r\n\
+              \r\n\
+              a,b\n1,2\n\r\n\
+              --XyZ--\r\n"
+        );
+        assert_eq!(form.content_length(), form.body.len());
+    }
+
+    #[test]
+    fn fixed_boundary_found_in_data_is_rejected() {
+        let result = MultipartBuilder::with_boundary("edge").text("note", "ends with --edge").build();
+        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+    }
+
+    #[test]
+    fn generated_boundary_avoids_part_data() {
+        let builder = MultipartBuilder::new().text("a", "one").text("b", "two");
+        let form = builder.build().unwrap();
+
+        let boundary = form.content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+        assert!(!builder.collides(boundary));
+        assert!(form.body.ends_with(format!("--{}--\r\n", boundary).as_bytes()));
+    }
+
+    #[test]
+    fn part_headers_with_line_breaks_are_rejected() {
+        let result = MultipartBuilder::new().file("f", "x\r\nInjected: 1", "text/plain", b"").build();
+        assert_eq!(result.unwrap_err(), Error::Http(HttpClientError::InvalidRequest));
+    }
+}
@@ -0,0 +1,24 @@
+use crate::error::{Error, IoPhase, Result, TransportError};
+use std::net::{SocketAddr, ToSocketAddrs};
+
+pub trait Resolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>>;
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = (host, port)
+            .to_socket_addrs()
+            .map_err(|e| Error::from_io(e, IoPhase::Resolve))?
+            .collect();
+
+        if addrs.is_empty() {
+            return Err(Error::Transport(TransportError::DnsFailure));
+        }
+
+        Ok(addrs)
+    }
+}
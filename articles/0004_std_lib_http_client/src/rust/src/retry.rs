@@ -0,0 +1,72 @@
+use std::time::Instant;
+
+// A token bucket shared by every request on a client. Each retry spends one token, and
+// tokens come back at a fixed rate, so a burst of failures (a server restarting, say)
+// cannot turn into a burst of retries: once the bucket is empty, failures are returned
+// as they happen.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    capacity: f64,
+    tokens: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl RetryBudget {
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            tokens: f64::from(capacity),
+            refill_per_second: refill_per_second.max(0.0),
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn available(&mut self) -> u32 {
+        self.refill();
+        self.tokens as u32
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let earned = now.duration_since(self.last_refill).as_secs_f64() * self.refill_per_second;
+        self.tokens = (self.tokens + earned).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn tokens_run_out_without_refill() {
+        let mut budget = RetryBudget::new(2, 0.0);
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+        assert_eq!(budget.available(), 0);
+    }
+
+    #[test]
+    fn tokens_refill_over_time_up_to_capacity() {
+        let mut budget = RetryBudget::new(3, 200.0);
+        while budget.try_acquire() {}
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(budget.available(), 3);
+        assert!(budget.try_acquire());
+    }
+}
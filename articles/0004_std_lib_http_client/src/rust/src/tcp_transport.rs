@@ -1,35 +1,232 @@
-use crate::error::{Error, Result, TransportError};
-use crate::transport::Transport;
-use std::io::{Read, Write};
-use std::net::{Shutdown, TcpStream};
-use std::os::unix::io::AsRawFd;
-use std::time::Duration;
-
-#[derive(Default)]
+use crate::error::{Error, IoPhase, Result, TransportError};
+use crate::resolver::{Resolver, SystemResolver};
+use crate::transport::{Transport, is_timeout, socket_has_input, time_until};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
 pub struct TcpTransport {
     stream: Option<TcpStream>,
+    nodelay: bool,
+    nonblocking: bool,
+    resolver: Box<dyn Resolver + Send + Sync>,
+    connect_stagger: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    bind_addr: Option<SocketAddr>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    read_deadline: Option<Instant>,
+    write_deadline: Option<Instant>,
+}
+
+impl Default for TcpTransport {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TcpTransport {
+    const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
     pub fn new() -> Self {
-        Self { stream: None }
+        Self {
+            stream: None,
+            nodelay: true,
+            nonblocking: false,
+            resolver: Box::new(SystemResolver),
+            connect_stagger: None,
+            connect_timeout: None,
+            read_timeout: None,
+            bind_addr: None,
+            cancel_token: None,
+            read_deadline: None,
+            write_deadline: None,
+        }
+    }
+
+    // Wraps a stream connected elsewhere (an event loop, socket activation); `connect`
+    // is not needed and its socket options are left as the caller set them.
+    pub fn from_stream(stream: TcpStream) -> Self {
+        let mut transport = Self::new();
+        transport.stream = Some(stream);
+        transport
+    }
+
+    pub fn set_resolver<R: Resolver + Send + Sync + 'static>(&mut self, resolver: R) {
+        self.resolver = Box::new(resolver);
+    }
+
+    // Subsequent connects originate from `addr`; only resolved addresses of the same
+    // family are tried.
+    pub fn bind_local(&mut self, addr: SocketAddr) {
+        self.bind_addr = Some(addr);
+    }
+
+    // With a stagger set, connect races the resolved addresses: each attempt starts one
+    // stagger after the last, or at once if every earlier one has failed, and the first to
+    // complete wins. None keeps sequential attempts.
+    pub fn set_connect_stagger(&mut self, stagger: Option<Duration>) {
+        self.connect_stagger = stagger;
+    }
+
+    // Bounds how long connect may take across all resolved addresses; expiry is TimedOut.
+    // Connects from a bind_local address are not covered.
+    pub fn set_connect_timeout(&mut self, timeout: Option<Duration>) {
+        self.connect_timeout = timeout;
+    }
+
+    pub fn set_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.nodelay = nodelay;
+        if let Some(stream) = &self.stream {
+            stream.set_nodelay(nodelay)?;
+        }
+        Ok(())
+    }
+
+    pub fn nodelay(&self) -> bool {
+        self.nodelay
+    }
+
+    // In non-blocking mode reads and writes that cannot make progress return WouldBlock
+    // instead of waiting, for callers driving the socket from their own event loop. See
+    // Http1Protocol::resume_response for picking a response back up.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking = nonblocking;
+        if let Some(stream) = &self.stream {
+            stream.set_nonblocking(nonblocking)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.read_timeout = timeout;
+        if let Some(stream) = &self.stream {
+            stream.set_read_timeout(self.socket_read_timeout())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    // Setting the token from another thread makes a blocked read return Cancelled.
+    // Reads wake every CANCEL_POLL_INTERVAL to check it, so the abort is not instant.
+    pub fn set_cancel_token(&mut self, token: Option<Arc<AtomicBool>>) -> Result<()> {
+        self.cancel_token = token;
+        if let Some(stream) = &self.stream {
+            stream.set_read_timeout(self.socket_read_timeout())?;
+        }
+        Ok(())
+    }
+
+    // Like close, but then waits up to `timeout` for the peer to finish sending and close its
+    // side, discarding anything still in flight. The socket is released either way.
+    pub fn close_graceful(&mut self, timeout: Duration) -> Result<()> {
+        let Some(mut stream) = self.stream.take() else {
+            return Ok(());
+        };
+        stream.flush().map_err(|e| Error::from_io(e, IoPhase::Close))?;
+        stream.shutdown(Shutdown::Write).map_err(|e| Error::from_io(e, IoPhase::Close))?;
+
+        let deadline = Instant::now() + timeout;
+        let mut scratch = [0u8; 4096];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Transport(TransportError::TimedOut));
+            }
+            stream.set_read_timeout(Some(remaining)).map_err(|e| Error::from_io(e, IoPhase::Close))?;
+            match stream.read(&mut scratch) {
+                Ok(0) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if is_timeout(&e) => {
+                    return Err(Error::Transport(TransportError::TimedOut));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(Error::from_io(e, IoPhase::Close)),
+            }
+        }
+    }
+
+    fn socket_read_timeout(&self) -> Option<Duration> {
+        match (&self.cancel_token, self.read_timeout) {
+            (Some(_), Some(timeout)) => Some(timeout.min(Self::CANCEL_POLL_INTERVAL)),
+            (Some(_), None) => Some(Self::CANCEL_POLL_INTERVAL),
+            (None, timeout) => timeout,
+        }
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        match &self.stream {
+            Some(stream) => stream.local_addr().map_err(|e| Error::from_io(e, IoPhase::Connect)),
+            None => Err(Error::Transport(TransportError::NotConnected)),
+        }
+    }
+
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        match &self.stream {
+            Some(stream) => stream.peer_addr().map_err(|e| Error::from_io(e, IoPhase::Connect)),
+            None => Err(Error::Transport(TransportError::NotConnected)),
+        }
+    }
+
+    pub fn as_raw_fd(&self) -> Option<RawFd> {
+        self.stream.as_ref().map(|stream| stream.as_raw_fd())
+    }
+}
+
+impl FromRawFd for TcpTransport {
+    // Safety: `fd` must be an open, connected TCP socket that nothing else owns.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_stream(unsafe { TcpStream::from_raw_fd(fd) })
     }
 }
 
 impl Transport for TcpTransport {
     fn connect(&mut self, host: &str, port: u16) -> Result<()> {
-        let addr = format!("{}:{}", host, port);
-        let stream = TcpStream::connect(addr)?;
+        let addrs = self.resolver.resolve(host, port)?;
+        let stream = match (self.bind_addr, self.connect_stagger) {
+            (Some(local), _) => connect_from(local, &addrs)?,
+            (None, Some(stagger)) if addrs.len() > 1 => race_connect(&addrs, stagger, self.connect_timeout)?,
+            _ => match self.connect_timeout {
+                Some(timeout) => connect_within(&addrs, timeout)?,
+                None => TcpStream::connect(&addrs[..]).map_err(|e| Error::from_io(e, IoPhase::Connect))?,
+            },
+        };
 
-        stream.set_nodelay(true)?;
+        stream.set_nodelay(self.nodelay).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        stream.set_read_timeout(self.socket_read_timeout()).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        stream.set_nonblocking(self.nonblocking).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
 
         self.stream = Some(stream);
         Ok(())
     }
 
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn input_pending(&self) -> bool {
+        self.stream.as_ref().is_some_and(|stream| socket_has_input(stream.as_raw_fd()))
+    }
+
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
         if let Some(stream) = &mut self.stream {
-            let bytes_written = stream.write(buf)?;
+            if let Some(deadline) = self.write_deadline {
+                stream.set_write_timeout(Some(time_until(deadline)?)).map_err(|e| Error::from_io(e, IoPhase::Write))?;
+            }
+            let bytes_written = stream.write(buf).map_err(|e| match self.write_deadline {
+                _ if self.nonblocking && e.kind() == ErrorKind::WouldBlock => Error::Transport(TransportError::WouldBlock),
+                Some(_) if is_timeout(&e) => Error::Transport(TransportError::TimedOut),
+                _ => Error::from_io(e, IoPhase::Write),
+            })?;
+            if bytes_written == 0 && !buf.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
             Ok(bytes_written)
         } else {
             Err(Error::Transport(TransportError::SocketWriteFailure))
@@ -37,32 +234,326 @@ impl Transport for TcpTransport {
     }
 
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if let Some(stream) = &mut self.stream {
-            let bytes_read = stream.read(buf)?;
-            if bytes_read == 0 && !buf.is_empty() {
-                return Err(Error::Transport(TransportError::ConnectionClosed));
+        // The deadline can only shorten whatever timeout is already configured.
+        let deadline_timeout = match self.read_deadline {
+            Some(deadline) => {
+                let remaining = time_until(deadline)?;
+                Some(self.socket_read_timeout().map_or(remaining, |timeout| timeout.min(remaining)))
             }
-            Ok(bytes_read)
-        } else {
-            Err(Error::Transport(TransportError::SocketReadFailure))
+            None => None,
+        };
+
+        let Some(stream) = &mut self.stream else {
+            return Err(Error::Transport(TransportError::SocketReadFailure));
+        };
+        if deadline_timeout.is_some() {
+            stream.set_read_timeout(deadline_timeout).map_err(|e| Error::from_io(e, IoPhase::Read))?;
         }
+
+        let started = Instant::now();
+        let bytes_read = loop {
+            if let Some(token) = &self.cancel_token
+                && token.load(Ordering::Acquire)
+            {
+                return Err(Error::Transport(TransportError::Cancelled));
+            }
+
+            match stream.read(buf) {
+                Ok(n) => break n,
+                Err(e) if self.nonblocking && e.kind() == ErrorKind::WouldBlock => {
+                    return Err(Error::Transport(TransportError::WouldBlock));
+                }
+                // A poll wakeup rather than the caller's own timeout; go round again.
+                Err(e) if self.cancel_token.is_some()
+                    && is_timeout(&e)
+                    && self.read_timeout.is_none_or(|timeout| started.elapsed() < timeout)
+                    && self.read_deadline.is_none_or(|deadline| Instant::now() < deadline) => {}
+                Err(e) if self.read_deadline.is_some() && is_timeout(&e) => {
+                    return Err(Error::Transport(TransportError::TimedOut));
+                }
+                Err(e) => return Err(Error::from_io(e, IoPhase::Read)),
+            }
+        };
+
+        if bytes_read == 0 && !buf.is_empty() {
+            return Err(Error::Transport(TransportError::ConnectionClosed));
+        }
+        Ok(bytes_read)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn supports_send_file(&self) -> bool {
+        true
     }
 
+    #[cfg(target_os = "linux")]
+    fn send_file(&mut self, fd: RawFd, offset: u64, len: u64) -> Result<()> {
+        let Some(stream) = &mut self.stream else {
+            return Err(Error::Transport(TransportError::SocketWriteFailure));
+        };
+        if let Some(deadline) = self.write_deadline {
+            stream.set_write_timeout(Some(time_until(deadline)?)).map_err(|e| Error::from_io(e, IoPhase::Write))?;
+        }
+
+        let mut position = libc::off_t::try_from(offset).map_err(|_| Error::Transport(TransportError::SocketWriteFailure))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let count = usize::try_from(remaining).unwrap_or(usize::MAX);
+            let sent = unsafe { libc::sendfile(stream.as_raw_fd(), fd, &mut position, count) };
+            if sent < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(match self.write_deadline {
+                    Some(_) if is_timeout(&err) => Error::Transport(TransportError::TimedOut),
+                    _ => Error::from_io(err, IoPhase::Write),
+                });
+            }
+            // End of file before `len` bytes: the file shrank after its size was taken, and
+            // the Content-Length already sent can no longer be honoured.
+            if sent == 0 {
+                return Err(Error::Transport(TransportError::SocketWriteFailure));
+            }
+            remaining -= sent as u64;
+        }
+        Ok(())
+    }
+
+    // Ends our side with a FIN once pending writes are flushed, without waiting on the peer.
     fn close(&mut self) -> Result<()> {
-        if let Some(stream) = self.stream.take() {
-            stream.shutdown(Shutdown::Both)?;
+        if let Some(mut stream) = self.stream.take() {
+            stream.flush().map_err(|e| Error::from_io(e, IoPhase::Close))?;
+            stream.shutdown(Shutdown::Write).map_err(|e| Error::from_io(e, IoPhase::Close))?;
+        }
+        Ok(())
+    }
+
+    fn set_read_deadline(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.read_deadline = deadline;
+        if deadline.is_none()
+            && let Some(stream) = &self.stream
+        {
+            stream.set_read_timeout(self.socket_read_timeout())?;
+        }
+        Ok(())
+    }
+
+    fn set_write_deadline(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.write_deadline = deadline;
+        if deadline.is_none()
+            && let Some(stream) = &self.stream
+        {
+            stream.set_write_timeout(None)?;
         }
         Ok(())
     }
 }
 
+fn connect_from(local: SocketAddr, addrs: &[SocketAddr]) -> Result<TcpStream> {
+    let mut last_error = Error::Transport(TransportError::SocketConnectFailure);
+
+    for &remote in addrs.iter().filter(|remote| remote.is_ipv4() == local.is_ipv4()) {
+        match bind_and_connect(local, remote) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Error::from_io(e, IoPhase::Connect),
+        }
+    }
+    Err(last_error)
+}
+
+// std offers no bind-before-connect, so the socket is assembled by hand.
+fn bind_and_connect(local: SocketAddr, remote: SocketAddr) -> std::io::Result<TcpStream> {
+    let domain = if remote.is_ipv4() { libc::AF_INET } else { libc::AF_INET6 };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Owned from here on, so every early return closes the descriptor.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let (local_storage, local_len) = to_sockaddr(local);
+    if unsafe { libc::bind(fd, &local_storage as *const _ as *const libc::sockaddr, local_len) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let (remote_storage, remote_len) = to_sockaddr(remote);
+    if unsafe { libc::connect(fd, &remote_storage as *const _ as *const libc::sockaddr, remote_len) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(TcpStream::from(socket))
+}
+
+fn to_sockaddr(addr: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { std::mem::zeroed() };
+
+    let len = match addr {
+        SocketAddr::V4(v4) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = v4.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(v4.ip().octets());
+            std::mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(v6) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = v6.port().to_be();
+            sin6.sin6_flowinfo = v6.flowinfo();
+            sin6.sin6_addr.s6_addr = v6.ip().octets();
+            sin6.sin6_scope_id = v6.scope_id();
+            std::mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+// Happy Eyeballs style (RFC 8305): attempts start on non-blocking sockets, one every
+// `stagger` or as soon as every pending attempt has failed, and the first to finish its
+// handshake wins. The losing sockets are closed on return, whatever the outcome.
+fn race_connect(addrs: &[SocketAddr], stagger: Duration, timeout: Option<Duration>) -> Result<TcpStream> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut pending: Vec<OwnedFd> = Vec::new();
+    let mut remaining = addrs.iter();
+    let mut next_start = Instant::now();
+    let mut last_error = None;
+
+    loop {
+        let now = Instant::now();
+        if (pending.is_empty() || now >= next_start)
+            && let Some(&addr) = remaining.next()
+        {
+            match start_connect(addr) {
+                Ok(socket) => pending.push(socket),
+                Err(e) => last_error = Some(e),
+            }
+            next_start = now + stagger;
+            continue;
+        }
+
+        if pending.is_empty() {
+            return Err(last_error.map_or(Error::Transport(TransportError::SocketConnectFailure), |e| {
+                Error::from_io(e, IoPhase::Connect)
+            }));
+        }
+
+        let mut wait = deadline.map(|deadline| deadline.saturating_duration_since(now));
+        if wait.is_some_and(|wait| wait.is_zero()) {
+            return Err(Error::Transport(TransportError::TimedOut));
+        }
+        if remaining.len() > 0 {
+            let until_next = next_start.saturating_duration_since(now);
+            wait = Some(wait.map_or(until_next, |wait| wait.min(until_next)));
+        }
+
+        let mut poll_fds: Vec<libc::pollfd> = pending
+            .iter()
+            .map(|socket| libc::pollfd { fd: socket.as_raw_fd(), events: libc::POLLOUT, revents: 0 })
+            .collect();
+        let poll_timeout = wait.map_or(-1, |wait| wait.as_millis().min(i32::MAX as u128) as i32);
+        let ready = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, poll_timeout) };
+        if ready < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(Error::from_io(err, IoPhase::Connect));
+        }
+
+        // Walked back to front so removing a finished attempt keeps earlier indices valid.
+        for i in (0..poll_fds.len()).rev() {
+            if poll_fds[i].revents == 0 {
+                continue;
+            }
+            let socket = pending.swap_remove(i);
+            match connect_result(&socket) {
+                Ok(()) => {
+                    let stream = TcpStream::from(socket);
+                    stream.set_nonblocking(false).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+                    return Ok(stream);
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+    }
+}
+
+// Opens a non-blocking socket and starts connecting it to `addr`.
+fn start_connect(addr: SocketAddr) -> std::io::Result<OwnedFd> {
+    let domain = if addr.is_ipv4() { libc::AF_INET } else { libc::AF_INET6 };
+    let fd = unsafe { libc::socket(domain, libc::SOCK_STREAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK, 0) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let (storage, len) = to_sockaddr(addr);
+    if unsafe { libc::connect(fd, &storage as *const _ as *const libc::sockaddr, len) } < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(err);
+        }
+    }
+    Ok(socket)
+}
+
+// How a non-blocking connect that poll reported as finished actually ended.
+fn connect_result(socket: &OwnedFd) -> std::io::Result<()> {
+    let mut error: libc::c_int = 0;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_ERROR,
+            &mut error as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if rc < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    match error {
+        0 => Ok(()),
+        errno => Err(std::io::Error::from_raw_os_error(errno)),
+    }
+}
+
+// Tries each address in turn, sharing `timeout` between them.
+fn connect_within(addrs: &[SocketAddr], timeout: Duration) -> Result<TcpStream> {
+    let deadline = Instant::now() + timeout;
+    let mut last_error = Error::Transport(TransportError::SocketConnectFailure);
+
+    for addr in addrs {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Transport(TransportError::TimedOut));
+        }
+        match TcpStream::connect_timeout(addr, remaining) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Error::from_io(e, IoPhase::Connect),
+        }
+    }
+    Err(last_error)
+}
+
+impl Drop for TcpTransport {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::error::{Error, TransportError};
     use std::io::{Read, Write};
     use std::net::{TcpListener, TcpStream};
+    use std::os::unix::io::AsRawFd;
     use std::thread;
+    use std::time::Duration;
 
     fn setup_test_server<F>(server_logic: F) -> (std::net::SocketAddr, thread::JoinHandle<()>)
     where
@@ -80,12 +571,246 @@ mod tests {
         (local_addr, handle)
     }
 
+    struct FixedResolver(Vec<SocketAddr>);
+
+    impl Resolver for FixedResolver {
+        fn resolve(&self, _host: &str, _port: u16) -> Result<Vec<SocketAddr>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn bind_local_sets_connection_source_address() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (addr, server_handle) = setup_test_server(move |stream| {
+            tx.send(stream.peer_addr().unwrap()).unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.bind_local("127.0.0.1:0".parse().unwrap());
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let local = transport.local_addr().unwrap();
+        assert!(local.is_ipv4());
+        assert_eq!(local.ip().to_string(), "127.0.0.1");
+        assert_ne!(local.port(), 0);
+        assert_eq!(transport.peer_addr().unwrap(), addr);
+        assert_eq!(rx.recv().unwrap(), local);
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn bind_local_rejects_mismatched_address_family() {
+        let (addr, _server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        transport.bind_local("[::1]:0".parse().unwrap());
+
+        let result = transport.connect(&addr.ip().to_string(), addr.port());
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::SocketConnectFailure));
+    }
+
+    #[test]
+    fn racing_connect_skips_black_hole_address() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        // A listener with a full zero-length backlog silently drops further SYNs, so a
+        // connect to it hangs just like one to an unreachable host.
+        let black_hole_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        unsafe { libc::listen(black_hole_listener.as_raw_fd(), 0) };
+        let black_hole = black_hole_listener.local_addr().unwrap();
+        let _backlog_filler = TcpStream::connect(black_hole).unwrap();
+
+        let mut transport = TcpTransport::new();
+        transport.set_resolver(FixedResolver(vec![black_hole, addr]));
+        transport.set_connect_stagger(Some(std::time::Duration::from_millis(50)));
+
+        let started = std::time::Instant::now();
+        transport.connect("racing.test", addr.port()).unwrap();
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(transport.peer_addr().unwrap(), addr);
+
+        transport.close().unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn racing_connect_reports_failure_when_all_attempts_fail() {
+        let refused: Vec<SocketAddr> = (0..2)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap())
+            .collect();
+
+        let mut transport = TcpTransport::new();
+        transport.set_resolver(FixedResolver(refused));
+        transport.set_connect_stagger(Some(std::time::Duration::from_millis(10)));
+
+        let result = transport.connect("racing.test", 0);
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::SocketConnectFailure));
+    }
+
+    #[test]
+    fn racing_connect_times_out_when_no_attempt_completes() {
+        let black_holes: Vec<(TcpListener, TcpStream)> = (0..2)
+            .map(|_| {
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                unsafe { libc::listen(listener.as_raw_fd(), 0) };
+                let filler = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+                (listener, filler)
+            })
+            .collect();
+        let addrs = black_holes.iter().map(|(listener, _)| listener.local_addr().unwrap()).collect();
+
+        let mut transport = TcpTransport::new();
+        transport.set_resolver(FixedResolver(addrs));
+        transport.set_connect_stagger(Some(Duration::from_millis(20)));
+        transport.set_connect_timeout(Some(Duration::from_millis(200)));
+
+        let started = std::time::Instant::now();
+        assert_eq!(transport.connect("racing.test", 0).unwrap_err(), Error::Transport(TransportError::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!transport.is_connected());
+    }
+
+    #[test]
+    fn racing_connect_moves_on_as_soon_as_an_attempt_fails() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+        let refused = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let mut transport = TcpTransport::new();
+        transport.set_resolver(FixedResolver(vec![refused, addr]));
+        // The stagger alone would hold the second attempt back far longer than the test allows.
+        transport.set_connect_stagger(Some(Duration::from_secs(30)));
+
+        let started = std::time::Instant::now();
+        transport.connect("racing.test", addr.port()).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(transport.peer_addr().unwrap(), addr);
+
+        transport.close().unwrap();
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn construction_succeeds() {
         let transport = TcpTransport::new();
         assert!(transport.stream.is_none());
     }
 
+    #[test]
+    fn is_connected_tracks_connect_and_close() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        assert!(!transport.is_connected());
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        assert!(transport.is_connected());
+        transport.close().unwrap();
+        assert!(!transport.is_connected());
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn input_pending_reports_unread_bytes_without_consuming_them() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |mut stream| {
+            stream.write_all(b"queued").unwrap();
+            rx.recv().unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let mut buf = [0u8; 6];
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !transport.input_pending() {
+            assert!(Instant::now() < deadline);
+            thread::sleep(Duration::from_millis(1));
+        }
+        transport.read(&mut buf).unwrap();
+        assert_eq!(&buf, b"queued");
+        assert!(!transport.input_pending());
+
+        tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn non_blocking_read_returns_would_block_until_data_arrives() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |mut stream| {
+            rx.recv().unwrap();
+            stream.write_all(b"ready").unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_nonblocking(true).unwrap();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let mut buf = [0u8; 16];
+        assert_eq!(transport.read(&mut buf).unwrap_err(), Error::Transport(TransportError::WouldBlock));
+
+        tx.send(()).unwrap();
+        let n = loop {
+            match transport.read(&mut buf) {
+                Err(Error::Transport(TransportError::WouldBlock)) => thread::sleep(Duration::from_millis(1)),
+                other => break other.unwrap(),
+            }
+        };
+        assert_eq!(&buf[..n], b"ready");
+        server_handle.join().unwrap();
+    }
+
+    fn tcp_nodelay_sockopt(transport: &TcpTransport) -> bool {
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let rc = unsafe {
+            libc::getsockopt(
+                transport.as_raw_fd().unwrap(),
+                libc::IPPROTO_TCP,
+                libc::TCP_NODELAY,
+                &mut value as *mut _ as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        assert_eq!(rc, 0);
+        value != 0
+    }
+
+    #[test]
+    fn nodelay_sockopt_follows_configuration_across_connect() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        transport.set_nodelay(false).unwrap();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        assert!(!tcp_nodelay_sockopt(&transport));
+
+        transport.set_nodelay(true).unwrap();
+        assert!(tcp_nodelay_sockopt(&transport));
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn nodelay_defaults_to_enabled_and_is_configurable() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        assert!(transport.nodelay());
+
+        transport.set_nodelay(false).unwrap();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        assert!(!transport.stream.as_ref().unwrap().nodelay().unwrap());
+
+        transport.set_nodelay(true).unwrap();
+        assert!(transport.stream.as_ref().unwrap().nodelay().unwrap());
+
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn connect_succeeds() {
         let (addr, server_handle) = setup_test_server(|_stream| {
@@ -100,6 +825,51 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn connect_uses_custom_resolver() {
+        use crate::http1_protocol::Http1Protocol;
+        use crate::http_protocol::{HttpMethod, HttpProtocol, HttpRequest};
+
+        struct StubResolver {
+            addr: std::net::SocketAddr,
+        }
+
+        impl Resolver for StubResolver {
+            fn resolve(&self, host: &str, _port: u16) -> Result<Vec<SocketAddr>> {
+                if host == "service.internal" {
+                    Ok(vec![self.addr])
+                } else {
+                    Err(Error::Transport(TransportError::DnsFailure))
+                }
+            }
+        }
+
+        let (addr, server_handle) = setup_test_server(|mut stream| {
+            let mut buffer = vec![0; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\nresolved").unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_resolver(StubResolver { addr });
+
+        let mut protocol = Http1Protocol::new(transport);
+        protocol.connect("service.internal", 8080).unwrap();
+
+        let request = HttpRequest {
+            method: HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+
+        let res = protocol.perform_request_unsafe(&request).unwrap();
+        assert_eq!(res.status_code, 200);
+        assert_eq!(res.body, b"resolved");
+
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn write_succeeds() {
         let (tx, rx) = std::sync::mpsc::channel();
@@ -145,6 +915,156 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn peer_addr_matches_server_address_after_connect() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        assert_eq!(transport.peer_addr().unwrap_err(), Error::Transport(TransportError::NotConnected));
+        assert_eq!(transport.local_addr().unwrap_err(), Error::Transport(TransportError::NotConnected));
+
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        assert_eq!(transport.peer_addr().unwrap(), addr);
+        let local_addr = transport.local_addr().unwrap();
+        assert_eq!(local_addr.ip(), addr.ip());
+        assert_ne!(local_addr.port(), addr.port());
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn cancel_token_aborts_blocked_request() {
+        use crate::http1_protocol::Http1Protocol;
+        use crate::http_protocol::{HttpMethod, HttpProtocol, HttpRequest};
+
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |mut stream| {
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer);
+            // Never answer; just hold the connection open until the test is done.
+            let _ = release_rx.recv();
+        });
+
+        let token = Arc::new(AtomicBool::new(false));
+        let mut transport = TcpTransport::new();
+        transport.set_cancel_token(Some(Arc::clone(&token))).unwrap();
+
+        let client_handle = thread::spawn(move || {
+            let mut protocol = Http1Protocol::new(transport);
+            protocol.connect(&addr.ip().to_string(), addr.port()).unwrap();
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            let result = protocol.perform_request_safe(&request).map(|_| ());
+            (result, Instant::now())
+        });
+
+        thread::sleep(Duration::from_millis(100));
+        let cancelled_at = Instant::now();
+        token.store(true, Ordering::Release);
+
+        let (result, returned_at) = client_handle.join().unwrap();
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::Cancelled));
+        assert!(returned_at.duration_since(cancelled_at) < Duration::from_millis(500));
+
+        release_tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn read_timeout_on_stalled_server_is_timed_out() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |_stream| {
+            let _ = release_rx.recv();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let mut buffer = [0u8; 16];
+        assert_eq!(transport.read(&mut buffer).unwrap_err(), Error::Transport(TransportError::TimedOut));
+
+        release_tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn read_timeout_still_applies_with_cancel_token() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |_stream| {
+            let _ = release_rx.recv();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.set_cancel_token(Some(Arc::new(AtomicBool::new(false)))).unwrap();
+        transport.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let mut buffer = [0u8; 16];
+        let started = Instant::now();
+        assert_eq!(
+            crate::transport::Transport::read(&mut transport, &mut buffer).unwrap_err(),
+            Error::Transport(TransportError::TimedOut)
+        );
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        release_tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn read_deadline_times_out_on_silent_server() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |_stream| {
+            let _ = release_rx.recv();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        transport.set_read_deadline(Some(Instant::now() + Duration::from_millis(50))).unwrap();
+
+        let mut buffer = [0u8; 16];
+        let started = Instant::now();
+        assert_eq!(transport.read(&mut buffer).unwrap_err(), Error::Transport(TransportError::TimedOut));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // Once passed, the deadline fails reads without touching the socket.
+        assert_eq!(transport.read(&mut buffer).unwrap_err(), Error::Transport(TransportError::TimedOut));
+
+        release_tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn from_stream_performs_request_without_connect() {
+        use crate::http1_protocol::Http1Protocol;
+        use crate::http_protocol::{HttpMethod, HttpProtocol, HttpRequest};
+        use std::os::unix::io::IntoRawFd;
+
+        for wrap_fd in [false, true] {
+            let (addr, server_handle) = setup_test_server(|mut stream| {
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer).unwrap();
+                stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nfd!").unwrap();
+            });
+
+            let stream = TcpStream::connect(addr).unwrap();
+            let transport = if wrap_fd {
+                unsafe { TcpTransport::from_raw_fd(stream.into_raw_fd()) }
+            } else {
+                TcpTransport::from_stream(stream)
+            };
+            assert_eq!(transport.peer_addr().unwrap(), addr);
+
+            let mut protocol = Http1Protocol::new(transport);
+            let request = HttpRequest { method: HttpMethod::Get, path: "/", body: &[], headers: vec![] };
+            let res = protocol.perform_request_safe(&request).unwrap();
+            assert_eq!(res.body, b"fd!");
+
+            server_handle.join().unwrap();
+        }
+    }
+
     #[test]
     fn close_succeeds() {
         let (addr, server_handle) = setup_test_server(|_stream| {});
@@ -158,6 +1078,96 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn close_graceful_delivers_large_payload() {
+        let payload: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let expected = payload.clone();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (addr, server_handle) = setup_test_server(move |mut stream| {
+            let mut received = Vec::new();
+            stream.read_to_end(&mut received).unwrap();
+            tx.send(received).unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let mut sent = 0;
+        while sent < payload.len() {
+            sent += crate::transport::Transport::write(&mut transport, &payload[sent..]).unwrap();
+        }
+
+        transport.close_graceful(Duration::from_secs(5)).unwrap();
+        assert!(transport.close_graceful(Duration::from_secs(5)).is_ok());
+
+        assert_eq!(rx.recv().unwrap(), expected);
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn close_graceful_times_out_when_peer_stays_open() {
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let (addr, server_handle) = setup_test_server(move |_stream| {
+            let _ = release_rx.recv();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+
+        let result = transport.close_graceful(Duration::from_millis(100));
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::TimedOut));
+        assert!(transport.as_raw_fd().is_none());
+
+        release_tx.send(()).unwrap();
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn drop_closes_connection_cleanly() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (addr, server_handle) = setup_test_server(move |mut stream| {
+            let mut buffer = [0u8; 16];
+            tx.send(stream.read(&mut buffer).map_err(|e| e.kind())).unwrap();
+        });
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        drop(transport);
+
+        assert_eq!(rx.recv().unwrap(), Ok(0));
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn drop_after_explicit_close_is_harmless() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        assert!(transport.close().is_ok());
+        drop(transport);
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn raw_fd_tracks_active_connection() {
+        let (addr, server_handle) = setup_test_server(|_stream| {});
+
+        let mut transport = TcpTransport::new();
+        assert_eq!(transport.as_raw_fd(), None);
+
+        transport.connect(&addr.ip().to_string(), addr.port()).unwrap();
+        let stream_fd = transport.stream.as_ref().unwrap().as_raw_fd();
+        assert_eq!(transport.as_raw_fd(), Some(stream_fd));
+
+        transport.close().unwrap();
+        assert_eq!(transport.as_raw_fd(), None);
+
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn close_is_idempotent() {
         let (addr, server_handle) = setup_test_server(|_stream| {});
@@ -186,6 +1196,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn connect_to_resolvable_host_on_dead_port_is_not_dns_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead_port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let mut transport = TcpTransport::new();
+        let result = transport.connect("localhost", dead_port);
+
+        assert_eq!(
+            result.unwrap_err(),
+            Error::Transport(TransportError::SocketConnectFailure)
+        );
+    }
 
     #[test]
     fn connect_fails_on_dns_failure() {
@@ -1,4 +1,6 @@
-use crate::error::Result;
+use crate::error::{Error, Result, TransportError};
+use std::os::unix::io::RawFd;
+use std::time::{Duration, Instant};
 
 pub trait Transport {
     fn connect(&mut self, host: &str, port: u16) -> Result<()>;
@@ -8,4 +10,62 @@ pub trait Transport {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
 
     fn close(&mut self) -> Result<()>;
-}
\ No newline at end of file
+
+    // Whether a connection is currently open. Transports that cannot tell report true
+    // and leave it to the first read or write.
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    // Whether bytes have already arrived and can be read without waiting. Transports
+    // that cannot tell report false.
+    fn input_pending(&self) -> bool {
+        false
+    }
+
+    // Whether send_file is implemented, so callers can check before committing to it.
+    fn supports_send_file(&self) -> bool {
+        false
+    }
+
+    // Sends `len` bytes of the file behind `fd`, starting at `offset`, without copying them
+    // through user space. Transports with no such path report Unsupported.
+    fn send_file(&mut self, _fd: RawFd, _offset: u64, _len: u64) -> Result<()> {
+        Err(Error::Transport(TransportError::Unsupported))
+    }
+
+    // Reads after `deadline` fail with TimedOut; None removes the deadline. Unlike a
+    // timeout this is absolute, so it bounds a whole phase spread over many reads.
+    // Transports without a clock of their own ignore it.
+    fn set_read_deadline(&mut self, _deadline: Option<Instant>) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_write_deadline(&mut self, _deadline: Option<Instant>) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Time left before `deadline`, or TimedOut once it has passed. Never zero, since socket
+// timeouts treat zero as an error.
+pub(crate) fn time_until(deadline: Instant) -> Result<Duration> {
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(Error::Transport(TransportError::TimedOut));
+    }
+    Ok(remaining)
+}
+
+// Peeks one byte without blocking; an empty queue, a closed peer or an error all
+// count as nothing pending.
+pub(crate) fn socket_has_input(fd: RawFd) -> bool {
+    let mut byte = 0u8;
+    let peeked = unsafe {
+        libc::recv(fd, (&mut byte as *mut u8).cast(), 1, libc::MSG_PEEK | libc::MSG_DONTWAIT)
+    };
+    peeked > 0
+}
+
+pub(crate) fn is_timeout(err: &std::io::Error) -> bool {
+    matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+}
@@ -1,37 +1,104 @@
-use crate::error::{Error, Result, TransportError};
-use crate::transport::Transport;
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
+use crate::error::{Error, IoPhase, Result, TransportError};
+use crate::transport::{Transport, is_timeout, socket_has_input, time_until};
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::net::{SocketAddr, UnixStream};
 use std::net::Shutdown;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Default)]
 pub struct UnixTransport {
     stream: Option<UnixStream>,
+    connect_timeout: Option<Duration>,
+    nonblocking: bool,
+    read_deadline: Option<Instant>,
+    write_deadline: Option<Instant>,
 }
 
 impl UnixTransport {
     pub fn new() -> Self {
-        Self { stream: None }
+        Self { stream: None, connect_timeout: None, nonblocking: false, read_deadline: None, write_deadline: None }
+    }
+
+    pub fn from_stream(stream: UnixStream) -> Self {
+        Self { stream: Some(stream), connect_timeout: None, nonblocking: false, read_deadline: None, write_deadline: None }
+    }
+
+    // Bounds connect, which otherwise blocks for as long as the listener's backlog is full.
+    pub fn set_connect_timeout(&mut self, timeout: Option<Duration>) {
+        self.connect_timeout = timeout;
+    }
+
+    // In non-blocking mode reads and writes that cannot make progress return WouldBlock
+    // instead of waiting, for callers driving the socket from their own event loop. See
+    // Http1Protocol::resume_response for picking a response back up.
+    pub fn set_nonblocking(&mut self, nonblocking: bool) -> Result<()> {
+        self.nonblocking = nonblocking;
+        if let Some(stream) = &self.stream {
+            stream.set_nonblocking(nonblocking)?;
+        }
+        Ok(())
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        match &self.stream {
+            Some(stream) => stream.local_addr().map_err(|e| Error::from_io(e, IoPhase::Connect)),
+            None => Err(Error::Transport(TransportError::NotConnected)),
+        }
+    }
+
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        match &self.stream {
+            Some(stream) => stream.peer_addr().map_err(|e| Error::from_io(e, IoPhase::Connect)),
+            None => Err(Error::Transport(TransportError::NotConnected)),
+        }
+    }
+
+    pub fn as_raw_fd(&self) -> Option<RawFd> {
+        self.stream.as_ref().map(|stream| stream.as_raw_fd())
+    }
+}
+
+impl FromRawFd for UnixTransport {
+    // Safety: `fd` must be an open, connected Unix stream socket that nothing else owns.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self::from_stream(unsafe { UnixStream::from_raw_fd(fd) })
     }
 }
 
 impl Transport for UnixTransport {
     fn connect(&mut self, path: &str, _port: u16) -> Result<()> {
-        match UnixStream::connect(path) {
-            Ok(stream) => {
-                self.stream = Some(stream);
-                Ok(())
-            }
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                Err(Error::Transport(TransportError::SocketConnectFailure))
-            }
-            Err(e) => Err(e.into()),
-        }
+        let stream = match self.connect_timeout {
+            Some(timeout) => connect_with_timeout(path, timeout)?,
+            None => UnixStream::connect(path).map_err(|e| Error::from_io(e, IoPhase::Connect))?,
+        };
+        stream.set_nonblocking(self.nonblocking).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    fn input_pending(&self) -> bool {
+        self.stream.as_ref().is_some_and(|stream| socket_has_input(stream.as_raw_fd()))
     }
 
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
         if let Some(stream) = &mut self.stream {
-            let bytes_written = stream.write(buf)?;
+            if let Some(deadline) = self.write_deadline {
+                stream.set_write_timeout(Some(time_until(deadline)?)).map_err(|e| Error::from_io(e, IoPhase::Write))?;
+            }
+            let bytes_written = stream.write(buf).map_err(|e| match self.write_deadline {
+                _ if self.nonblocking && e.kind() == ErrorKind::WouldBlock => Error::Transport(TransportError::WouldBlock),
+                Some(_) if is_timeout(&e) => Error::Transport(TransportError::TimedOut),
+                _ => Error::from_io(e, IoPhase::Write),
+            })?;
+            if bytes_written == 0 && !buf.is_empty() {
+                return Err(Error::Transport(TransportError::ConnectionClosed));
+            }
             Ok(bytes_written)
         } else {
             Err(Error::Transport(TransportError::SocketWriteFailure))
@@ -40,7 +107,14 @@ impl Transport for UnixTransport {
 
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         if let Some(stream) = &mut self.stream {
-            let bytes_read = stream.read(buf)?;
+            if let Some(deadline) = self.read_deadline {
+                stream.set_read_timeout(Some(time_until(deadline)?)).map_err(|e| Error::from_io(e, IoPhase::Read))?;
+            }
+            let bytes_read = stream.read(buf).map_err(|e| match self.read_deadline {
+                _ if self.nonblocking && e.kind() == ErrorKind::WouldBlock => Error::Transport(TransportError::WouldBlock),
+                Some(_) if is_timeout(&e) => Error::Transport(TransportError::TimedOut),
+                _ => Error::from_io(e, IoPhase::Read),
+            })?;
             if bytes_read == 0 && !buf.is_empty() {
                 return Err(Error::Transport(TransportError::ConnectionClosed));
             }
@@ -52,10 +126,87 @@ impl Transport for UnixTransport {
 
     fn close(&mut self) -> Result<()> {
         if let Some(stream) = self.stream.take() {
-            stream.shutdown(Shutdown::Both)?;
+            stream.shutdown(Shutdown::Both).map_err(|e| Error::from_io(e, IoPhase::Close))?;
         }
         Ok(())
     }
+
+    fn set_read_deadline(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.read_deadline = deadline;
+        if deadline.is_none()
+            && let Some(stream) = &self.stream
+        {
+            stream.set_read_timeout(None)?;
+        }
+        Ok(())
+    }
+
+    fn set_write_deadline(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.write_deadline = deadline;
+        if deadline.is_none()
+            && let Some(stream) = &self.stream
+        {
+            stream.set_write_timeout(None)?;
+        }
+        Ok(())
+    }
+}
+
+// A non-blocking Unix socket reports a full backlog as EAGAIN rather than as a connect in
+// progress, so there is nothing to poll for: the attempt is repeated until it goes through
+// or the deadline passes, and the stream is made blocking again afterwards.
+fn connect_with_timeout(path: &str, timeout: Duration) -> Result<UnixStream> {
+    const RETRY_INTERVAL: Duration = Duration::from_millis(5);
+    let deadline = Instant::now() + timeout;
+    let (addr, addr_len) = to_sockaddr_un(path).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+
+    let fd = unsafe { libc::socket(libc::AF_UNIX, libc::SOCK_STREAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK, 0) };
+    if fd < 0 {
+        return Err(Error::from_io(std::io::Error::last_os_error(), IoPhase::Connect));
+    }
+    // Owned from here on, so every early return closes the descriptor.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    while unsafe { libc::connect(fd, &addr as *const _ as *const libc::sockaddr, addr_len) } < 0 {
+        let err = std::io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EINTR) => continue,
+            Some(libc::EAGAIN) => {}
+            _ => return Err(Error::from_io(err, IoPhase::Connect)),
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Transport(TransportError::TimedOut));
+        }
+        thread::sleep(remaining.min(RETRY_INTERVAL));
+    }
+
+    let stream = UnixStream::from(socket);
+    stream.set_nonblocking(false).map_err(|e| Error::from_io(e, IoPhase::Connect))?;
+    Ok(stream)
+}
+
+fn to_sockaddr_un(path: &str) -> std::io::Result<(libc::sockaddr_un, libc::socklen_t)> {
+    let mut addr: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+
+    // The zeroed tail supplies the terminating NUL, so the path must leave room for it.
+    let bytes = path.as_bytes();
+    if bytes.len() >= addr.sun_path.len() || bytes.contains(&0) {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid socket path"));
+    }
+    for (dst, &src) in addr.sun_path.iter_mut().zip(bytes) {
+        *dst = src as libc::c_char;
+    }
+
+    let len = std::mem::size_of::<libc::sa_family_t>() + bytes.len() + 1;
+    Ok((addr, len as libc::socklen_t))
+}
+
+impl Drop for UnixTransport {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
 }
 
 #[cfg(test)]
@@ -107,6 +258,76 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn is_connected_tracks_connect_and_close() {
+        let (path, server_handle) = setup_unix_test_server(|_stream| {});
+        let mut transport = UnixTransport::new();
+        assert!(!transport.is_connected());
+        transport.connect(&path, 0).unwrap();
+        assert!(transport.is_connected());
+        transport.close().unwrap();
+        assert!(!transport.is_connected());
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn connect_with_timeout_succeeds() {
+        let (path, server_handle) = setup_unix_test_server(|_stream| {});
+        let mut transport = UnixTransport::new();
+        transport.set_connect_timeout(Some(Duration::from_secs(1)));
+        transport.connect(&path, 0).unwrap();
+
+        // The stream is handed back in blocking mode.
+        let mut buf = [0u8; 1];
+        assert_eq!(transport.read(&mut buf).unwrap_err(), Error::Transport(TransportError::ConnectionClosed));
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn connect_timeout_expires_while_backlog_is_full() {
+        let socket_path = format!("/tmp/httpc_rust_test_{}_{}", std::process::id(), TEST_COUNTER.fetch_add(1, Ordering::SeqCst));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // With a zero backlog the listener queues one connection and turns the rest away
+        // until it accepts, which it never does.
+        unsafe { libc::listen(listener.as_raw_fd(), 0) };
+        let _backlog_filler = UnixStream::connect(&socket_path).unwrap();
+
+        let mut transport = UnixTransport::new();
+        transport.set_connect_timeout(Some(Duration::from_millis(100)));
+        let started = Instant::now();
+        let result = transport.connect(&socket_path, 0);
+
+        assert_eq!(result.unwrap_err(), Error::Transport(TransportError::TimedOut));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!transport.is_connected());
+        std::fs::remove_file(&socket_path).unwrap();
+    }
+
+    #[test]
+    fn from_stream_wraps_one_end_of_a_socket_pair() {
+        let (client_end, mut server_end) = UnixStream::pair().unwrap();
+        let server_handle = thread::spawn(move || {
+            let mut buffer = [0u8; 1024];
+            let _ = server_end.read(&mut buffer).unwrap();
+            server_end.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\npair").unwrap();
+        });
+
+        let mut protocol = crate::http1_protocol::Http1Protocol::new(UnixTransport::from_stream(client_end));
+        let request = crate::http_protocol::HttpRequest {
+            method: crate::http_protocol::HttpMethod::Get,
+            path: "/",
+            body: &[],
+            headers: vec![],
+        };
+        let res = crate::http_protocol::HttpProtocol::perform_request_safe(&mut protocol, &request).unwrap();
+        assert_eq!(res.body, b"pair");
+
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn write_succeeds() {
         let (tx, rx) = mpsc::channel();
@@ -145,6 +366,21 @@ mod tests {
         server_handle.join().unwrap();
     }
 
+    #[test]
+    fn peer_addr_matches_server_path_after_connect() {
+        let (path, handle) = setup_unix_test_server(|_| {});
+        let mut transport = UnixTransport::new();
+        assert_eq!(transport.peer_addr().unwrap_err(), Error::Transport(TransportError::NotConnected));
+        assert_eq!(transport.local_addr().unwrap_err(), Error::Transport(TransportError::NotConnected));
+
+        transport.connect(&path, 0).unwrap();
+
+        let peer_addr = transport.peer_addr().unwrap();
+        assert_eq!(peer_addr.as_pathname(), Some(std::path::Path::new(&path)));
+        assert!(transport.local_addr().unwrap().is_unnamed());
+        handle.join().unwrap();
+    }
+
     #[test]
     fn close_succeeds() {
         let (path, handle) = setup_unix_test_server(|_| {});
@@ -154,6 +390,51 @@ mod tests {
         handle.join().unwrap();
     }
 
+    #[test]
+    fn drop_closes_connection_cleanly() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (path, server_handle) = setup_unix_test_server(move |mut stream| {
+            let mut buffer = [0u8; 16];
+            tx.send(stream.read(&mut buffer).map_err(|e| e.kind())).unwrap();
+        });
+
+        let mut transport = UnixTransport::new();
+        transport.connect(&path, 0).unwrap();
+        drop(transport);
+
+        assert_eq!(rx.recv().unwrap(), Ok(0));
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn drop_after_explicit_close_is_harmless() {
+        let (path, server_handle) = setup_unix_test_server(|_stream| {});
+
+        let mut transport = UnixTransport::new();
+        transport.connect(&path, 0).unwrap();
+        assert!(transport.close().is_ok());
+        drop(transport);
+
+        server_handle.join().unwrap();
+    }
+
+    #[test]
+    fn raw_fd_tracks_active_connection() {
+        let (path, server_handle) = setup_unix_test_server(|_stream| {});
+
+        let mut transport = UnixTransport::new();
+        assert_eq!(transport.as_raw_fd(), None);
+
+        transport.connect(&path, 0).unwrap();
+        let stream_fd = transport.stream.as_ref().unwrap().as_raw_fd();
+        assert_eq!(transport.as_raw_fd(), Some(stream_fd));
+
+        transport.close().unwrap();
+        assert_eq!(transport.as_raw_fd(), None);
+
+        server_handle.join().unwrap();
+    }
+
     #[test]
     fn close_is_idempotent() {
         let (path, handle) = setup_unix_test_server(|_| {});
@@ -209,4 +490,22 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), Error::Transport(TransportError::ConnectionClosed));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn read_deadline_times_out_on_silent_server() {
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let (path, handle) = setup_unix_test_server(move |_stream| {
+            let _ = release_rx.recv();
+        });
+
+        let mut transport = UnixTransport::new();
+        transport.connect(&path, 0).unwrap();
+        transport.set_read_deadline(Some(Instant::now() + Duration::from_millis(50))).unwrap();
+
+        let mut buf = [0u8; 32];
+        assert_eq!(transport.read(&mut buf).unwrap_err(), Error::Transport(TransportError::TimedOut));
+
+        release_tx.send(()).unwrap();
+        handle.join().unwrap();
+    }
+}